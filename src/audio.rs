@@ -0,0 +1,66 @@
+use std::f32::consts::TAU;
+
+use crate::chordpro::charts::Chart;
+
+const DEFAULT_TEMPO: u32 = 120;
+const DEFAULT_BEATS: u8 = 4;
+/// The length of each click in seconds.
+const CLICK_LENGTH: f32 = 0.02;
+
+impl Chart {
+    /// Generates one bar of click track as mono samples between -1 and 1, to
+    /// be played in a loop. The first beat has a higher, louder click than the
+    /// rest. The tempo comes from the `{tempo}` directive and the number of
+    /// beats from the `{time}` directive, or 120 BPM and four beats if unset.
+    pub fn metronome_samples(&self, sample_rate: u32) -> Vec<f32> {
+        let tempo = self.tempo().unwrap_or(DEFAULT_TEMPO).max(1);
+        let beats = self
+            .time_signature()
+            .map_or(DEFAULT_BEATS, |(beats, _)| beats);
+        let beat_length = (sample_rate as u64 * 60 / tempo as u64) as usize;
+        let click_length = ((sample_rate as f32 * CLICK_LENGTH) as usize).min(beat_length);
+
+        let mut samples = vec![0.0; beat_length * beats as usize];
+        for beat in 0..beats as usize {
+            let (frequency, volume) = if beat == 0 {
+                (1760.0, 1.0)
+            } else {
+                (880.0, 0.5)
+            };
+            let click = &mut samples[beat * beat_length..][..click_length];
+            for (i, sample) in click.iter_mut().enumerate() {
+                // A sine wave that fades out over the length of the click.
+                let fade = 1.0 - i as f32 / click_length as f32;
+                *sample = volume * fade * (TAU * frequency * i as f32 / sample_rate as f32).sin();
+            }
+        }
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chordpro::charts::Chart;
+
+    #[test]
+    fn test_metronome_samples() {
+        let chart = "{tempo: 90}\n{time: 3/4}\n[G]Lorem"
+            .parse::<Chart>()
+            .unwrap();
+        let samples = chart.metronome_samples(44_100);
+
+        assert_eq!(samples.len(), 44_100 * 60 / 90 * 3);
+        assert!(samples.iter().all(|sample| (-1.0..=1.0).contains(sample)));
+        let peak = |beat: usize| {
+            samples[beat * 44_100 * 60 / 90..][..882]
+                .iter()
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()))
+        };
+        assert!(peak(0) > 0.9);
+        assert!((0.4..0.6).contains(&peak(1)));
+        assert_eq!(samples[1000], 0.0);
+
+        let default = "[G]Lorem".parse::<Chart>().unwrap();
+        assert_eq!(default.metronome_samples(8_000).len(), 8_000 * 60 / 120 * 4);
+    }
+}
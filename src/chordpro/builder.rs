@@ -0,0 +1,163 @@
+use crate::{
+    chordpro::{
+        charts::{Chart, Chunk, Line},
+        directives::{Directive, SectionKind},
+    },
+    theory::{chords::Chord, scales::Scale},
+};
+
+/// Builds a [`Chart`] in code. Metadata directives such as the title and key
+/// always come before the content, while sections and lines are kept in the
+/// order they are added.
+///
+/// Chunks added with [`ChartBuilder::chord_over`] and [`ChartBuilder::lyrics`]
+/// collect into the current line until [`ChartBuilder::newline`] is called.
+#[derive(Debug, Clone, Default)]
+pub struct ChartBuilder {
+    header: Vec<Line>,
+    body: Vec<Line>,
+    current_line: Vec<Chunk>,
+    open_section: Option<SectionKind>,
+}
+
+impl ChartBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn title(self, title: &str) -> Self {
+        self.directive(Directive::Title(title.to_owned()))
+    }
+
+    pub fn subtitle(self, subtitle: &str) -> Self {
+        self.directive(Directive::Subtitle(subtitle.to_owned()))
+    }
+
+    pub fn artist(self, artist: &str) -> Self {
+        self.directive(Directive::Artist(artist.to_owned()))
+    }
+
+    pub fn key(self, key: Scale) -> Self {
+        self.directive(Directive::Key(key))
+    }
+
+    pub fn tempo(self, bpm: u32) -> Self {
+        self.directive(Directive::Tempo { bpm, text: None })
+    }
+
+    /// Adds a directive to the header, before any content.
+    pub fn directive(mut self, directive: Directive) -> Self {
+        self.header.push(Line::Directive(directive));
+        self
+    }
+
+    /// Starts a section, ending the previous one if it is still open.
+    pub fn section(mut self, kind: SectionKind, label: Option<&str>) -> Self {
+        self.end_section();
+        self.body.push(Line::Directive(Directive::SectionStart {
+            kind,
+            label: label.map(str::to_owned),
+        }));
+        self.open_section = Some(kind);
+        self
+    }
+
+    /// Adds a whole line of chunks, after finishing the current line.
+    pub fn line(mut self, chunks: Vec<Chunk>) -> Self {
+        self.finish_line();
+        self.body.push(Line::Content {
+            chunks,
+            inline: true,
+        });
+        self
+    }
+
+    /// Adds a chord with the lyrics it is played over to the current line.
+    pub fn chord_over(mut self, chord: Chord, lyrics: &str) -> Self {
+        self.current_line.push(Chunk {
+            chord: Some(chord),
+            lyrics: lyrics.to_owned(),
+        });
+        self
+    }
+
+    /// Adds lyrics without a chord to the current line.
+    pub fn lyrics(mut self, lyrics: &str) -> Self {
+        self.current_line.push(Chunk {
+            chord: None,
+            lyrics: lyrics.to_owned(),
+        });
+        self
+    }
+
+    /// Finishes the current line. If it has no chunks, this adds a blank line.
+    pub fn newline(mut self) -> Self {
+        let chunks = std::mem::take(&mut self.current_line);
+        self.line(chunks)
+    }
+
+    pub fn build(mut self) -> Chart {
+        self.end_section();
+        let mut lines = self.header;
+        lines.append(&mut self.body);
+        Chart::new(lines)
+    }
+
+    fn finish_line(&mut self) {
+        if !self.current_line.is_empty() {
+            self.body.push(Line::Content {
+                chunks: std::mem::take(&mut self.current_line),
+                inline: true,
+            });
+        }
+    }
+
+    fn end_section(&mut self) {
+        self.finish_line();
+        if let Some(kind) = self.open_section.take() {
+            self.body
+                .push(Line::Directive(Directive::SectionEnd { kind }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chordpro::{builder::ChartBuilder, charts::Chart, directives::SectionKind},
+        theory::{chords::Chord, notes::Letter::*, scales::Scale},
+    };
+
+    #[test]
+    fn test_build() {
+        let chart = ChartBuilder::new()
+            .title("Lorem")
+            .section(SectionKind::Verse, Some("Verse 1"))
+            .lyrics("Lorem ")
+            .chord_over(Chord::major(G.natural()), "ipsum ")
+            .chord_over(Chord::minor(E.natural()), "dolor")
+            .newline()
+            .newline()
+            .section(SectionKind::Chorus, None)
+            .chord_over(Chord::major(C.natural()), "sit amet")
+            .key(Scale(G.natural()))
+            .build();
+
+        let parsed = "{title:Lorem}\n{key:G}\n{start_of_verse:Verse 1}\nLorem [G]ipsum [Em]dolor\n\n{end_of_verse}\n{start_of_chorus}\n[C]sit amet\n{end_of_chorus}"
+            .parse::<Chart>()
+            .unwrap();
+        assert_eq!(chart, parsed);
+    }
+
+    #[test]
+    fn test_build_and_transpose() {
+        let mut chart = ChartBuilder::new()
+            .key(Scale(G.natural()))
+            .chord_over(Chord::major(G.natural()), "Lorem ")
+            .chord_over(Chord::major(D.natural()).over(F.sharp()), "ipsum")
+            .build();
+        chart.transpose_to(Scale(A.natural())).unwrap();
+
+        assert_eq!(chart.to_string(), "{key:A}\n[A]Lorem [E/G#]ipsum\n");
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{
+    chordpro::charts::Chart,
+    theory::{
+        chords::{Chord, ChordQuality},
+        fingerings::is_same_shape,
+        notes::{Letter::*, LetterNote, Note},
+        scales::Scale,
+    },
+};
+
+/// The open chords that can be played without a barre.
+const EASY_SHAPES: &[(LetterNote, &str)] = &[
+    (C.natural(), ""),
+    (C.natural(), "7"),
+    (G.natural(), ""),
+    (G.natural(), "7"),
+    (D.natural(), ""),
+    (D.natural(), "7"),
+    (A.natural(), ""),
+    (A.natural(), "7"),
+    (E.natural(), ""),
+    (E.natural(), "7"),
+    (A.natural(), "m"),
+    (A.natural(), "m7"),
+    (E.natural(), "m"),
+    (E.natural(), "m7"),
+    (D.natural(), "m"),
+    (D.natural(), "m7"),
+];
+
+/// The highest capo position that is suggested.
+const MAX_CAPO: u8 = 7;
+
+/// The chord shapes to play with a capo on a particular fret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapoSuggestion {
+    pub capo: u8,
+    /// The song's unique chords as they are fingered with the capo on.
+    pub shapes: Vec<Chord>,
+    /// How many of the shapes are open chords.
+    pub easy_shapes: usize,
+}
+
+impl Chart {
+    /// Ranks the capo positions from 0 to 7 by how many of the song's chords
+    /// become open chord shapes, such as G or Am, with the capo on. Ties go
+    /// to the lower capo position.
+    pub fn suggest_capo(&self) -> Vec<CapoSuggestion> {
+        let chords = self.unique_chords();
+        let key = self.key().or_else(|| self.detect_key());
+        let mut suggestions = (0..=MAX_CAPO)
+            .map(|capo| {
+                let shapes = chords
+                    .iter()
+                    .map(|chord| capo_shape(chord, key, capo))
+                    .collect::<Vec<_>>();
+                let easy_shapes = shapes.iter().filter(|shape| is_easy(shape)).count();
+                CapoSuggestion {
+                    capo,
+                    shapes,
+                    easy_shapes,
+                }
+            })
+            .collect::<Vec<_>>();
+        suggestions.sort_by_key(|suggestion| std::cmp::Reverse(suggestion.easy_shapes));
+        suggestions
+    }
+}
+
+/// The shape played for `chord` with the capo on fret `capo`, spelled in the
+/// key the shapes are played in if the song's key is known. Chords written
+/// as numbers are left as they are.
+fn capo_shape(chord: &Chord, key: Option<Scale>, capo: u8) -> Chord {
+    let lower = |note: Note| match (note, key) {
+        (Note::Letter(note), Some(key)) => {
+            let shape_key = Scale((key.0.as_midi() + -(capo as i8)).as_letter());
+            note.as_scale_degree(key).in_key(shape_key).into()
+        }
+        (Note::Letter(note), None) => (note.as_midi() + -(capo as i8)).as_letter().into(),
+        (note, _) => note,
+    };
+    Chord {
+        root: lower(chord.root),
+        quality: chord.quality.clone(),
+        bass: chord.bass.map(lower),
+        annotation: chord.annotation.clone(),
+    }
+}
+
+fn is_easy(chord: &Chord) -> bool {
+    EASY_SHAPES.iter().any(|&(root, quality)| {
+        let shape = Chord {
+            root: root.into(),
+            quality: ChordQuality(quality.to_owned()),
+            bass: None,
+            annotation: None,
+        };
+        is_same_shape(&shape, chord)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chordpro::charts::Chart;
+
+    #[test]
+    fn test_suggest_capo() {
+        let chart = "{key:Eb}\n[Eb]Lorem [Ab]ipsum [Bb7]dolor [Cm]sit [Eb/G]amet"
+            .parse::<Chart>()
+            .unwrap();
+        let suggestions = chart.suggest_capo();
+
+        assert_eq!(suggestions.len(), 8);
+        let best = &suggestions[0];
+        assert_eq!(best.capo, 1);
+        assert_eq!(best.easy_shapes, 4);
+        assert_eq!(
+            best.shapes
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["D", "G", "A7", "Bm", "D/F#"]
+        );
+
+        let open = suggestions.iter().find(|s| s.capo == 0).unwrap();
+        assert_eq!(open.easy_shapes, 0);
+    }
+
+    #[test]
+    fn test_suggest_capo_ties() {
+        let chart = "[G]Lorem [C]ipsum".parse::<Chart>().unwrap();
+        let suggestions = chart.suggest_capo();
+
+        assert_eq!(suggestions[0].capo, 0);
+        assert_eq!(suggestions[0].easy_shapes, 2);
+    }
+}
@@ -1,19 +1,76 @@
-use std::fmt::{self, Write};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    error::Error,
+    fmt::{self, Write},
+    ops::RangeBounds,
+    str::FromStr,
+};
+
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
-    chordpro::directives::Directive,
-    theory::{chords::Chord, notes::Note, scales::Scale},
+    chordpro::{
+        directives::{ChordDefinition, Directive},
+        parser,
+    },
+    theory::{
+        chords::Chord,
+        notes::{Accidental, Note, NoteNaming, Spelling},
+        scales::{Mode, Scale, ScaleDegree},
+    },
 };
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Chart {
     pub lines: Vec<Line>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) source: Source,
+}
+
+/// What is known about the text a chart was parsed from. This is not part of
+/// the chart's content, so it is ignored when comparing charts.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Source {
+    /// Where each line started in the source, by index into `lines`. Lines
+    /// added since, and lines past the end of this list, have no position.
+    pub(crate) positions: Vec<Option<LineCol>>,
+    /// The line ending used by most lines of the source, either
+    /// [`LineEnding::Lf`] or [`LineEnding::CrLf`].
+    pub(crate) line_ending: LineEnding,
+}
+
+impl PartialEq for Source {
+    fn eq(&self, _other: &Source) -> bool {
+        true
+    }
 }
 
+impl Eq for Source {}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Line {
     Directive(Directive),
-    Content { chunks: Vec<Chunk>, inline: bool },
+    Content {
+        chunks: Vec<Chunk>,
+        inline: bool,
+    },
+    /// A line inside a tab or grid section, kept exactly as written. This
+    /// includes the `\r` of a `\r\n` line ending, which is always written
+    /// back out as `\r\n`.
+    Verbatim(String),
+}
+
+/// A position in the source text. Both numbers start from 1, and the column
+/// counts characters rather than bytes. Positions refer to the text as it was
+/// written, before any continuation lines are joined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineCol {
+    pub line: u32,
+    pub column: usize,
 }
 
 impl Line {
@@ -21,17 +78,92 @@ impl Line {
         match self {
             Line::Directive { .. } => false,
             Line::Content { chunks, .. } => chunks.is_empty(),
+            Line::Verbatim(text) => text.trim_end_matches('\r').is_empty(),
+        }
+    }
+
+    /// Switches this line between inline chords and chords above. Lines that
+    /// are not lyric lines are left alone.
+    pub fn set_inline(&mut self, inline: bool) {
+        if let Line::Content { inline: i, .. } = self {
+            *i = inline;
         }
     }
+
+    /// The width in columns of the widest row this line renders as.
+    pub fn alignment_width(&self) -> usize {
+        self.to_string()
+            .lines()
+            .map(|row| row.width())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Replaces the chords on this line with those in `chord_line`, a row of
+    /// chords positioned by column over the existing lyrics. The lyrics are
+    /// left untouched.
+    pub fn replace_chords_from(&mut self, chord_line: &str) -> Result<(), String> {
+        let Line::Content { chunks, .. } = self else {
+            return Err("cannot replace the chords of a directive".to_owned());
+        };
+
+        let chords = parser::parse_chord_row(chord_line, &Default::default())?;
+        let lyrics = chunks
+            .iter()
+            .map(|chunk| chunk.lyrics.as_str())
+            .collect::<String>();
+        *chunks = if !chords.is_empty() {
+            parser::attach_chords(&chords, &lyrics)
+        } else if !lyrics.is_empty() {
+            vec![Chunk {
+                chord: None,
+                lyrics,
+            }]
+        } else {
+            Vec::new()
+        };
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Chunk {
     pub chord: Option<Chord>,
     pub lyrics: String,
 }
 
+/// The reason an operation that needs the chart's key could not be performed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyError {
+    /// The chart has no `{key}` directive and no key could be detected from
+    /// its chords.
+    Missing,
+    /// The chart is declared keyless with `{key: none}`.
+    DeclaredKeyless,
+}
+
+/// What [`Chart::transpose_with_capo`] does with a `{capo}` directive. Either
+/// way, the chords are written at the pitch they sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CapoTransposition {
+    /// Moves the capo so that the same shapes are played in the new key. The
+    /// capo is removed if it would end up at the nut.
+    KeepShapes,
+    /// Removes the capo, so the chords are played as written.
+    ConcertPitch,
+}
+
 impl Chart {
+    /// A chart with the given lines and no source positions, as for a chart
+    /// built in code.
+    pub fn new(lines: Vec<Line>) -> Chart {
+        Chart {
+            lines,
+            source: Source::default(),
+        }
+    }
+
     pub fn title(&self) -> Option<&str> {
         for line in &self.lines {
             if let Line::Directive(Directive::Title(title)) = line {
@@ -41,28 +173,207 @@ impl Chart {
         None
     }
 
+    /// The title to sort by, which is the `{sorttitle}` if present and the
+    /// display title otherwise.
+    pub fn sort_title(&self) -> Option<&str> {
+        for line in &self.lines {
+            if let Line::Directive(Directive::SortTitle(title)) = line {
+                return Some(title);
+            }
+        }
+        self.title()
+    }
+
+    pub fn subtitle(&self) -> Option<&str> {
+        for line in &self.lines {
+            if let Line::Directive(Directive::Subtitle(subtitle)) = line {
+                return Some(subtitle);
+            }
+        }
+        None
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        for line in &self.lines {
+            if let Line::Directive(Directive::Artist(artist)) = line {
+                return Some(artist);
+            }
+        }
+        None
+    }
+
+    pub fn composer(&self) -> Option<&str> {
+        for line in &self.lines {
+            if let Line::Directive(Directive::Composer(composer)) = line {
+                return Some(composer);
+            }
+        }
+        None
+    }
+
+    pub fn album(&self) -> Option<&str> {
+        for line in &self.lines {
+            if let Line::Directive(Directive::Album(album)) = line {
+                return Some(album);
+            }
+        }
+        None
+    }
+
     pub fn comment(&self) -> Option<&str> {
         for line in &self.lines {
-            if let Line::Directive(Directive::Comment(comment)) = line {
-                return Some(comment);
+            if let Line::Directive(Directive::Comment { text, .. }) = line {
+                return Some(text);
             }
         }
         None
     }
 
+    /// The key from the `{key}` directive, or failing that from a
+    /// `{meta: key ...}` directive.
     pub fn key(&self) -> Option<Scale> {
         for line in &self.lines {
             if let &Line::Directive(Directive::Key(key)) = line {
                 return Some(key);
             }
         }
+        self.meta_value("key")
+    }
+
+    pub fn capo(&self) -> Option<u8> {
+        for line in &self.lines {
+            if let &Line::Directive(Directive::Capo(capo)) = line {
+                return Some(capo);
+            }
+        }
+        None
+    }
+
+    /// The chord shapes declared with `{define}` or `{chord}`, in order.
+    pub fn chord_definitions(&self) -> impl Iterator<Item = &ChordDefinition> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Directive(Directive::Define(definition) | Directive::Chord(definition)) => {
+                Some(definition)
+            }
+            _ => None,
+        })
+    }
+
+    /// The number of columns from the `{columns}` directive.
+    pub fn columns(&self) -> Option<u8> {
+        for line in &self.lines {
+            if let &Line::Directive(Directive::Columns(columns)) = line {
+                return Some(columns);
+            }
+        }
         None
     }
 
+    /// Rewrites the chords as the shapes played with the capo on, then removes
+    /// the capo. The `{key}` directive keeps the sounding key.
+    pub fn apply_capo(&mut self) {
+        let Some(capo) = self.capo() else {
+            return;
+        };
+        let semitones = -(capo as i8);
+
+        match self.key() {
+            Some(key) => {
+                let shape_key = Scale((key.0.as_midi() + semitones).as_letter());
+                self.transform_all_notes(|note| match note {
+                    Note::Letter(n) => n.as_scale_degree(key).in_key(shape_key).into(),
+                    Note::Number(_) => *note,
+                });
+            }
+            None => self.transform_all_notes(|note| match note {
+                Note::Letter(n) => (n.as_midi() + semitones).as_letter().into(),
+                Note::Number(_) => *note,
+            }),
+        }
+
+        self.remove_directive(|directive| matches!(directive, Directive::Capo(_)));
+    }
+
+    /// The tempo from the `{tempo}` directive, or failing that from a
+    /// `{meta: tempo ...}` directive.
+    pub fn tempo(&self) -> Option<u32> {
+        for line in &self.lines {
+            if let &Line::Directive(Directive::Tempo { bpm, .. }) = line {
+                return Some(bpm);
+            }
+        }
+        self.meta_value("tempo")
+    }
+
+    /// The time signature from the `{time}` directive, as beats per bar and
+    /// beat unit.
+    pub fn time_signature(&self) -> Option<(u8, u8)> {
+        self.lines.iter().find_map(|line| match *line {
+            Line::Directive(Directive::Time { beats, unit }) => Some((beats, unit)),
+            _ => None,
+        })
+    }
+
+    /// The value of the first `{meta: name value}` directive with the given
+    /// name. Failing that, an unknown directive written as `{name: value}`,
+    /// such as `{ccli: 7195204}`, is used.
+    pub fn meta(&self, name: &str) -> Option<&str> {
+        let meta = self.lines.iter().find_map(|line| match line {
+            Line::Directive(Directive::Meta { name: n, value }) if n == name => {
+                Some(value.as_str())
+            }
+            _ => None,
+        });
+        meta.or_else(|| {
+            self.lines.iter().find_map(|line| match line {
+                Line::Directive(Directive::Other(content)) => content
+                    .split_once(':')
+                    .filter(|(n, _)| n.trim() == name)
+                    .map(|(_, value)| value.trim()),
+                _ => None,
+            })
+        })
+    }
+
+    fn meta_value<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.meta(name)?.trim().parse().ok()
+    }
+
+    /// Whether the chart is declared keyless with `{key: none}`.
+    pub fn is_declared_keyless(&self) -> bool {
+        self.lines
+            .iter()
+            .any(|line| matches!(line, Line::Directive(Directive::NoKey)))
+    }
+
+    /// Guesses the key from the chords in the chart, ignoring any `{key}`
+    /// directive. See [`Scale::detect`].
+    pub fn detect_key(&self) -> Option<Scale> {
+        Scale::detect(self.chords())
+    }
+
+    /// The declared key, or else the detected key.
+    fn require_key(&self) -> Result<Scale, KeyError> {
+        match self.key() {
+            Some(key) => Ok(key),
+            None if self.is_declared_keyless() => Err(KeyError::DeclaredKeyless),
+            None => self.detect_key().ok_or(KeyError::Missing),
+        }
+    }
+
     pub fn set_key(&mut self, key: Scale) {
         for line in &mut self.lines {
-            if let Line::Directive(Directive::Key(k)) = line {
-                *k = key;
+            if let Line::Directive(directive @ (Directive::Key(_) | Directive::NoKey)) = line {
+                *directive = Directive::Key(key);
+                return;
+            }
+        }
+        for line in &mut self.lines {
+            if let Line::Directive(Directive::Meta { name, value }) = line
+                && name == "key"
+                && value.trim().parse::<Scale>().is_ok()
+            {
+                *value = key.to_string();
                 return;
             }
         }
@@ -72,29 +383,454 @@ impl Chart {
             .iter()
             .position(|line| !matches!(line, Line::Directive(_)))
             .unwrap_or(self.lines.len());
-        self.lines
-            .insert(after_directives, Line::Directive(Directive::Key(key)));
+        self.insert_line(after_directives, Line::Directive(Directive::Key(key)));
     }
 
+    /// Replaces the first `{title}` directive, or inserts one at the top of
+    /// the chart if there is none.
+    pub fn set_title(&mut self, title: &str) {
+        for line in &mut self.lines {
+            if let Line::Directive(Directive::Title(old)) = line {
+                *old = title.to_owned();
+                return;
+            }
+        }
+        self.insert_line(0, Line::Directive(Directive::Title(title.to_owned())));
+    }
+
+    /// Replaces the first `{tempo}` directive, or failing that a
+    /// `{meta: tempo ...}` directive, and otherwise adds a `{tempo}` after the
+    /// directives at the top of the chart as [`Chart::set_key`] does.
+    pub fn set_tempo(&mut self, bpm: u32) {
+        for line in &mut self.lines {
+            if let Line::Directive(directive @ Directive::Tempo { .. }) = line {
+                *directive = Directive::Tempo { bpm, text: None };
+                return;
+            }
+        }
+        for line in &mut self.lines {
+            if let Line::Directive(Directive::Meta { name, value }) = line
+                && name == "tempo"
+                && value.trim().parse::<u32>().is_ok()
+            {
+                *value = bpm.to_string();
+                return;
+            }
+        }
+
+        let after_directives = self
+            .lines
+            .iter()
+            .position(|line| !matches!(line, Line::Directive(_)))
+            .unwrap_or(self.lines.len());
+        self.insert_line(
+            after_directives,
+            Line::Directive(Directive::Tempo { bpm, text: None }),
+        );
+    }
+
+    /// Removes every directive for which `predicate` returns `true`.
+    pub fn remove_directive(&mut self, mut predicate: impl FnMut(&Directive) -> bool) {
+        let keep = self
+            .lines
+            .iter()
+            .map(|line| match line {
+                Line::Directive(directive) => !predicate(directive),
+                _ => true,
+            })
+            .collect::<Vec<_>>();
+        let mut keep_line = keep.iter();
+        self.lines.retain(|_| *keep_line.next().unwrap());
+        let mut keep_position = keep.iter();
+        self.source
+            .positions
+            .retain(|_| *keep_position.next().unwrap());
+    }
+
+    /// Inserts `line` before the line at `index`, shifting the lines after it
+    /// down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the number of lines.
+    pub fn insert_line(&mut self, index: usize, line: Line) {
+        self.lines.insert(index, line);
+        if index <= self.source.positions.len() {
+            self.source.positions.insert(index, None);
+        }
+    }
+
+    /// Where the line at `index` started in the source, if the chart came
+    /// from the parser and the line has not been added since.
+    pub fn position(&self, index: usize) -> Option<LineCol> {
+        self.source.positions.get(index).copied().flatten()
+    }
+
+    /// The line ending used by most lines of the source, for writing the
+    /// chart back out with [`LineEnding::Detect`]. Charts that were not
+    /// parsed use [`LineEnding::Lf`].
+    pub fn line_ending(&self) -> LineEnding {
+        self.source.line_ending
+    }
+
+    /// The chunks of each content line, in order.
+    pub fn content_lines(&self) -> impl Iterator<Item = &[Chunk]> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Content { chunks, .. } => Some(chunks.as_slice()),
+            Line::Directive(_) | Line::Verbatim(_) => None,
+        })
+    }
+
+    /// Pairs up the lines of two charts for rendering side by side. If one chart
+    /// is longer, its remaining lines are paired with `None`.
+    pub fn parallel<'a>(
+        &'a self,
+        other: &'a Chart,
+    ) -> impl Iterator<Item = (Option<&'a Line>, Option<&'a Line>)> {
+        let len = self.lines.len().max(other.lines.len());
+        (0..len).map(|i| (self.lines.get(i), other.lines.get(i)))
+    }
+
+    /// Every chord in the chart, in order.
+    pub fn chords(&self) -> impl Iterator<Item = &Chord> {
+        self.content_lines()
+            .flatten()
+            .filter_map(|chunk| chunk.chord.as_ref())
+    }
+
+    /// Every distinct chord in the chart, in the order they first appear.
+    pub fn unique_chords(&self) -> Vec<Chord> {
+        let mut chords = Vec::new();
+        for chord in self.chords() {
+            if !chords.contains(chord) {
+                chords.push(chord.clone());
+            }
+        }
+        chords
+    }
+
+    /// How many times each chord is played.
+    pub fn chord_histogram(&self) -> BTreeMap<Chord, usize> {
+        let mut histogram = BTreeMap::new();
+        for chord in self.chords() {
+            *histogram.entry(chord.clone()).or_default() += 1;
+        }
+        histogram
+    }
+
+    /// The chords that use notes from outside `key`, such as borrowed chords
+    /// and secondary dominants, along with the position of the line they are
+    /// on. Every note of the chord is checked, including the bass, so D7 is
+    /// out of the key of C because of its F#. Notes are compared by pitch, and
+    /// numbered chords by their degrees in `key`. Chords on lines that did not
+    /// come from the parser are still listed, without a position.
+    pub fn non_diatonic_chords(&self, key: Scale) -> Vec<(Option<LineCol>, Chord)> {
+        let scale = key
+            .notes()
+            .map(|note| note.as_midi().as_int().rem_euclid(12));
+        let is_diatonic = |chord: &Chord| {
+            chord
+                .pitches(key)
+                .iter()
+                .all(|pitch| scale.contains(&pitch.as_int().rem_euclid(12)))
+        };
+
+        let mut chords = Vec::new();
+        for (index, line) in self.lines.iter().enumerate() {
+            let Line::Content { chunks, .. } = line else {
+                continue;
+            };
+            for chord in chunks.iter().filter_map(|chunk| chunk.chord.as_ref()) {
+                if !is_diatonic(chord) {
+                    chords.push((self.position(index), chord.clone()));
+                }
+            }
+        }
+        chords
+    }
+
+    /// How many chords have their root on each degree of `key`, ignoring any
+    /// bass note.
+    pub fn scale_degree_histogram(&self, key: Scale) -> BTreeMap<ScaleDegree, usize> {
+        let mut histogram = BTreeMap::new();
+        for chord in self.chords() {
+            *histogram
+                .entry(chord.root.as_scale_degree(key))
+                .or_default() += 1;
+        }
+        histogram
+    }
+
+    /// Renders the chart with each chord left out if it is the same as the
+    /// chord in the same position on the previous line with chords.
+    pub fn to_changes_only(&self) -> String {
+        let mut chart = self.clone();
+        let mut previous = Vec::new();
+        for line in &mut chart.lines {
+            let Line::Content { chunks, .. } = line else {
+                continue;
+            };
+            let chords = chunks
+                .iter()
+                .filter_map(|chunk| chunk.chord.clone())
+                .collect::<Vec<_>>();
+            if chords.is_empty() {
+                continue;
+            }
+
+            for (i, chunk) in chunks
+                .iter_mut()
+                .filter(|chunk| chunk.chord.is_some())
+                .enumerate()
+            {
+                if previous.get(i) == chunk.chord.as_ref() {
+                    chunk.chord = None;
+                }
+            }
+            previous = chords;
+        }
+        chart.to_string()
+    }
+
+    /// Switches every lyric line between inline chords and chords above. See
+    /// [`ChartDisplayOptions::chord_separator`] for keeping chords with no
+    /// lyrics apart once they are inline.
     pub fn set_inline(&mut self, inline: bool) {
+        self.set_inline_range(.., inline);
+    }
+
+    /// Switches the lyric lines in `range`, indexed into [`Chart::lines`],
+    /// between inline chords and chords above, leaving the rest of the chart
+    /// as it is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn set_inline_range(&mut self, range: impl RangeBounds<usize>, inline: bool) {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        for line in &mut self.lines[range] {
+            line.set_inline(inline);
+        }
+    }
+
+    /// Moves each chord in a "chords above" line to the start of the nearest
+    /// word, so that chords no longer split words in two.
+    pub fn quantize_chords(&mut self) {
         for line in &mut self.lines {
-            if let Line::Content { inline: i, .. } = line {
-                *i = inline;
+            let Line::Content {
+                chunks,
+                inline: false,
+                ..
+            } = line
+            else {
+                continue;
+            };
+
+            let lyrics = chunks
+                .iter()
+                .map(|chunk| chunk.lyrics.as_str())
+                .collect::<String>();
+            let word_starts = lyrics
+                .char_indices()
+                .filter(|&(i, c)| {
+                    !c.is_whitespace() && (i == 0 || lyrics[..i].ends_with(char::is_whitespace))
+                })
+                .map(|(i, _)| lyrics[..i].width())
+                .collect::<Vec<_>>();
+            let lyrics_width = lyrics.width();
+
+            let mut index = 0;
+            let mut chords = Vec::new();
+            for chunk in chunks.iter() {
+                if let Some(chord) = &chunk.chord {
+                    let snapped = if index < lyrics_width {
+                        word_starts
+                            .iter()
+                            .copied()
+                            .min_by_key(|&start| start.abs_diff(index))
+                            .unwrap_or(index)
+                    } else {
+                        index
+                    };
+                    chords.push((snapped, chord.clone()));
+                }
+                index += chunk.lyrics.width();
+            }
+
+            if !chords.is_empty() {
+                *chunks = parser::attach_chords(&chords, &lyrics);
             }
         }
     }
 
-    pub fn to_numbers(&mut self) {
-        let key = self
-            .key()
-            .expect("cannot convert to numbered notation without a key");
-        self.transform_all_notes(|note| note.as_scale_degree(key).into());
+    pub fn to_numbers(&mut self) -> Result<(), KeyError> {
+        self.to_numbers_in_mode(Mode::Major)
+    }
+
+    /// Converts to numbers counted from the key's tonic in the given mode.
+    pub fn to_numbers_in_mode(&mut self, mode: Mode) -> Result<(), KeyError> {
+        let key = self.require_key()?;
+        self.transform_all_notes(|note| note.as_scale_degree_in_mode(key, mode).into());
+        Ok(())
+    }
+
+    pub fn transpose_to(&mut self, new_key: Scale) -> Result<(), KeyError> {
+        self.transpose_to_with_spelling(new_key, Spelling::FollowKey)
     }
 
-    pub fn transpose_to(&mut self, new_key: Scale) {
-        let old_key = self.key().expect("cannot transpose without a key");
-        self.transform_all_notes(|note| note.as_scale_degree(old_key).in_key(new_key).into());
+    pub fn transpose_to_with_spelling(
+        &mut self,
+        new_key: Scale,
+        spelling: Spelling,
+    ) -> Result<(), KeyError> {
+        let old_key = self.require_key()?;
+        self.transform_all_notes(|note| {
+            let note = note.as_scale_degree(old_key).in_key(new_key);
+            note.as_midi().spelled(spelling).unwrap_or(note).into()
+        });
         self.set_key(new_key);
+        Ok(())
+    }
+
+    /// Transposes the chart by a number of semitones, moving each chord with
+    /// [`Chord::transpose`] and respelling it in the new key where it fits the
+    /// scale. Numbered chords are unchanged, and the new key is spelled with
+    /// flats where there is a choice.
+    pub fn transpose_by(&mut self, semitones: i8) -> Result<(), KeyError> {
+        let old_key = self.require_key()?;
+        let new_key = Scale((old_key.0.as_midi() + semitones).as_letter());
+        self.transform_all_chords(|chord| {
+            chord
+                .transpose(semitones, Spelling::FollowKey)
+                .spelled_in(new_key)
+        });
+        self.set_key(new_key);
+        Ok(())
+    }
+
+    /// Transposes the chart to `new_key`, adjusting or removing any capo as
+    /// chosen by `capo`. Plain [`Chart::transpose_to`] leaves the capo where
+    /// it is, which changes the shapes played.
+    pub fn transpose_with_capo(
+        &mut self,
+        new_key: Scale,
+        capo: CapoTransposition,
+    ) -> Result<(), KeyError> {
+        let old_key = self.require_key()?;
+        self.transpose_to(new_key)?;
+        let Some(old_capo) = self.capo() else {
+            return Ok(());
+        };
+
+        let new_capo = match capo {
+            CapoTransposition::KeepShapes => {
+                let shift = new_key.0.as_midi().as_int() - old_key.0.as_midi().as_int();
+                (old_capo as i8 + shift).rem_euclid(12) as u8
+            }
+            CapoTransposition::ConcertPitch => 0,
+        };
+        if new_capo == 0 {
+            self.remove_directive(|directive| matches!(directive, Directive::Capo(_)));
+        } else {
+            for line in &mut self.lines {
+                if let Line::Directive(Directive::Capo(capo)) = line {
+                    *capo = new_capo;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_letters(&mut self) -> Result<(), KeyError> {
+        let key = self.require_key()?;
+        self.transform_all_notes(|note| note.as_scale_degree(key).in_key(key).into());
+        Ok(())
+    }
+
+    /// Respells notes with double sharps or flats, such as those left by
+    /// transposing a chromatic chord into a key with many sharps. A note that
+    /// sounds the same as a note of `key` takes its spelling from the key, so
+    /// F## becomes G in C major. Otherwise it is spelled with the sharps or
+    /// flats of the key signature. Cb, Fb, E# and B# are also respelled as
+    /// naturals unless they are in `key`. Numbered chords are unchanged.
+    pub fn simplify_accidentals(&mut self, key: Scale) {
+        let scale = key.notes();
+        let signature = key.key_signature().map_or(0, |(count, _)| count);
+        self.transform_all_notes(|&note| {
+            let Note::Letter(note) = note else {
+                return note;
+            };
+            if scale.contains(&note) {
+                return note.into();
+            }
+            if let Some(&in_key) = scale.iter().find(|n| n.enharmonic_eq(note)) {
+                return in_key.into();
+            }
+
+            let accidental = note.accidental().as_int();
+            let sharps = match signature {
+                0 => accidental > 0,
+                signature => signature > 0,
+            };
+            let spelling = if sharps {
+                Spelling::Sharps
+            } else {
+                Spelling::Flats
+            };
+            let simplest = note.as_midi().spelled(spelling).unwrap_or(note);
+            if accidental.abs() > 1 || simplest.accidental() == Accidental::NATURAL {
+                simplest.into()
+            } else {
+                note.into()
+            }
+        });
+    }
+
+    /// Reduces every chord to its triad, as in [`Chord::simplified`].
+    pub fn simplify_chords(&mut self) {
+        self.transform_all_chords(Chord::simplified);
+    }
+
+    /// Removes all chords, leaving only the lyrics.
+    pub fn strip_chords(&mut self) {
+        for line in &mut self.lines {
+            if let Line::Content { chunks, inline, .. } = line {
+                let lyrics = chunks
+                    .iter()
+                    .map(|chunk| chunk.lyrics.as_str())
+                    .collect::<String>();
+                *chunks = if !lyrics.is_empty() {
+                    vec![Chunk {
+                        chord: None,
+                        lyrics,
+                    }]
+                } else {
+                    Vec::new()
+                };
+                *inline = true;
+            }
+        }
+    }
+
+    /// Merges neighbouring chunks without chords into one, and removes chunks
+    /// with neither a chord nor lyrics. Chunks with chords are left as they
+    /// are, so no lyrics move from one chord to another.
+    pub fn normalize(&mut self) {
+        for line in &mut self.lines {
+            let Line::Content { chunks, .. } = line else {
+                continue;
+            };
+
+            let mut normalized: Vec<Chunk> = Vec::with_capacity(chunks.len());
+            for chunk in chunks.drain(..) {
+                match (chunk.chord.is_none(), normalized.last_mut()) {
+                    (true, _) if chunk.lyrics.is_empty() => {}
+                    (true, Some(last)) if last.chord.is_none() => last.lyrics += &chunk.lyrics,
+                    _ => normalized.push(chunk),
+                }
+            }
+            *chunks = normalized;
+        }
     }
 
     fn transform_all_notes<F>(&mut self, mut f: F)
@@ -104,7 +840,8 @@ impl Chart {
         self.transform_all_chords(|chord| Chord {
             root: f(&chord.root),
             quality: chord.quality.clone(),
-            bass: chord.bass.as_ref().map(|b| f(b)),
+            bass: chord.bass.as_ref().map(&mut f),
+            annotation: chord.annotation.clone(),
         });
     }
 
@@ -124,6 +861,11 @@ impl Chart {
     }
 }
 
+/// Sorts charts by their sort title. Charts without a title come first.
+pub fn sort_by_title(charts: &mut [Chart]) {
+    charts.sort_by(|a, b| a.sort_title().cmp(&b.sort_title()));
+}
+
 impl fmt::Display for Chart {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for line in &self.lines {
@@ -133,43 +875,203 @@ impl fmt::Display for Chart {
     }
 }
 
-impl fmt::Display for Line {
+/// Options for writing a chart with [`Chart::display_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChartDisplayOptions {
+    /// The least number of spaces after a chord in the "chords above" format
+    /// before the next chord.
+    pub min_gap: usize,
+    /// Whether to insert spaces into the lyrics to keep each chord above its
+    /// lyrics when chords are too close together. Otherwise the lyrics are
+    /// left intact and the crowded chords are moved to the right.
+    pub pad_lyrics: bool,
+    /// Whether to draw a diagram of each chord at the top of a printed song.
+    pub show_diagrams: bool,
+    /// Whether to draw a vertical rule down the left of each section of a
+    /// printed song.
+    pub section_rules: bool,
+    /// Written between bracketed chords with no lyrics between them, as in
+    /// `[G] [D]`, so that they do not run together. This only affects inline
+    /// lines, including "chords above" lines after [`Chart::set_inline`], and
+    /// is empty by default so that charts are written as they were parsed.
+    pub chord_separator: Cow<'static, str>,
+    /// The number of spaces written before both the chords and the lyrics of
+    /// each "chords above" line, so that the chart can be indented without
+    /// changing its lyrics.
+    pub left_margin: usize,
+    /// The line ending to write after each line.
+    pub line_ending: LineEnding,
+    /// Whether to write the row of chords below the lyrics instead of above
+    /// them in "chords above" lines. The chords are lined up the same way.
+    pub chords_below: bool,
+    /// The names to write the notes of chords and the key with.
+    pub note_naming: NoteNaming,
+}
+
+/// How to end each line when writing a chart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LineEnding {
+    /// `\n`, as used on Unix.
+    #[default]
+    Lf,
+    /// `\r\n`, as used on Windows.
+    CrLf,
+    /// Whichever of the two the chart was parsed with.
+    Detect,
+}
+
+impl LineEnding {
+    /// The line ending found on most lines of `input`, or [`LineEnding::Lf`]
+    /// if there are no more `\r\n` line endings than `\n` ones.
+    pub fn detect(input: &str) -> LineEnding {
+        let crlf = input.matches("\r\n").count();
+        if crlf > input.matches('\n').count() - crlf {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Lf | LineEnding::Detect => "\n",
+        }
+    }
+}
+
+impl Default for ChartDisplayOptions {
+    fn default() -> Self {
+        Self {
+            min_gap: 1,
+            pad_lyrics: true,
+            show_diagrams: false,
+            section_rules: false,
+            chord_separator: Cow::Borrowed(""),
+            left_margin: 0,
+            line_ending: LineEnding::Lf,
+            chords_below: false,
+            note_naming: NoteNaming::English,
+        }
+    }
+}
+
+/// A chart written with particular [`ChartDisplayOptions`].
+pub struct ChartDisplay<'a> {
+    chart: &'a Chart,
+    options: ChartDisplayOptions,
+}
+
+impl Chart {
+    pub fn display_with(&self, options: ChartDisplayOptions) -> ChartDisplay<'_> {
+        ChartDisplay {
+            chart: self,
+            options,
+        }
+    }
+}
+
+impl fmt::Display for ChartDisplay<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line_ending = match self.options.line_ending {
+            LineEnding::Detect => self.chart.source.line_ending,
+            line_ending => line_ending,
+        };
+        let options = ChartDisplayOptions {
+            line_ending,
+            ..self.options.clone()
+        };
+        for line in &self.chart.lines {
+            line.fmt_with(f, &options)?;
+            match line {
+                Line::Verbatim(text) if text.ends_with('\r') => writeln!(f)?,
+                _ => write!(f, "{}", line_ending.as_str())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Line {
+    fn fmt_with(&self, f: &mut fmt::Formatter, options: &ChartDisplayOptions) -> fmt::Result {
         match self {
+            Line::Directive(Directive::Key(key)) => {
+                write!(f, "{{key:{}}}", key.0.named(options.note_naming))
+            }
             Line::Directive(directive) => write!(f, "{directive}"),
-            Line::Content { chunks, inline } => {
+            Line::Verbatim(text) => write!(f, "{text}"),
+            Line::Content { chunks, inline, .. } => {
                 if *inline {
-                    for chunk in chunks {
-                        write!(f, "{chunk}")?;
+                    for (i, chunk) in chunks.iter().enumerate() {
+                        chunk.fmt_with(f, options)?;
+                        if chunk.chord.is_some()
+                            && chunk.lyrics.is_empty()
+                            && chunks.get(i + 1).is_some_and(|next| next.chord.is_some())
+                        {
+                            write!(f, "{}", options.chord_separator)?;
+                        }
                     }
                 } else {
                     let mut index = 0;
+                    // The column of the first of any chords with no lyrics of
+                    // their own, which share the lyrics of the next chunk.
+                    let mut stacked_chords = None;
                     let mut chord_line = String::new();
                     let mut lyric_line = String::new();
                     for chunk in chunks {
+                        if !options.pad_lyrics {
+                            index = index.max(lyric_line.width());
+                        }
                         if chunk.chord.is_some() {
-                            while chord_line.len() < index {
+                            while chord_line.width() < index {
                                 chord_line.push(' ');
                             }
                         }
                         if !chunk.lyrics.is_empty() {
-                            while lyric_line.len() < index {
+                            let start = stacked_chords.take().unwrap_or(index);
+                            while lyric_line.width() < start && options.pad_lyrics {
                                 lyric_line.push(' ');
                             }
+                        } else if chunk.chord.is_some() {
+                            stacked_chords.get_or_insert(index);
                         }
 
                         if let Some(chord) = &chunk.chord {
-                            write!(&mut chord_line, "{chord}")?;
-                            index = chord_line.len() + 1;
+                            write!(&mut chord_line, "{}", chord.named(options.note_naming))?;
+                            index = chord_line.width() + options.min_gap;
                         }
                         lyric_line.push_str(&chunk.lyrics);
-                        index = index.max(lyric_line.len());
+                        if options.pad_lyrics {
+                            index = index.max(lyric_line.width());
+                        }
                     }
 
-                    if !chord_line.is_empty() {
-                        writeln!(f, "{chord_line}")?;
+                    // Without a row of chords, the lyrics are read back as an
+                    // inline line, so brackets must be escaped.
+                    if chord_line.is_empty() {
+                        lyric_line = escape_lyrics(&lyric_line);
+                    }
+                    let margin = " ".repeat(options.left_margin);
+                    let line_ending = options.line_ending.as_str();
+                    if options.chords_below {
+                        if !lyric_line.is_empty() {
+                            write!(f, "{margin}{lyric_line}")?;
+                        }
+                        if !lyric_line.is_empty() && !chord_line.is_empty() {
+                            write!(f, "{line_ending}")?;
+                        }
+                        if !chord_line.is_empty() {
+                            write!(f, "{margin}{chord_line}")?;
+                        }
+                    } else {
+                        if !chord_line.is_empty() {
+                            write!(f, "{margin}{chord_line}{line_ending}")?;
+                        }
+                        if !lyric_line.is_empty() {
+                            write!(f, "{margin}{lyric_line}")?;
+                        }
                     }
-                    write!(f, "{lyric_line}")?;
                 }
                 Ok(())
             }
@@ -177,27 +1079,847 @@ impl fmt::Display for Line {
     }
 }
 
-impl fmt::Display for Chunk {
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_with(f, &ChartDisplayOptions::default())
+    }
+}
+
+impl fmt::Display for KeyError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyError::Missing => write!(f, "chart has no key"),
+            KeyError::DeclaredKeyless => write!(f, "chart is keyless by declaration"),
+        }
+    }
+}
+
+impl Error for KeyError {}
+
+impl Chunk {
+    fn fmt_with(&self, f: &mut fmt::Formatter, options: &ChartDisplayOptions) -> fmt::Result {
         if let Some(chord) = &self.chord {
-            write!(f, "[{chord}]")?;
+            write!(f, "[{}]", chord.named(options.note_naming))?;
         }
-        write!(f, "{}", self.lyrics)
+        write!(f, "{}", escape_lyrics(&self.lyrics))
+    }
+}
+
+/// Escapes the backslashes and brackets in inline lyrics, so that they are
+/// not read as chords.
+fn escape_lyrics(lyrics: &str) -> String {
+    lyrics
+        .replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+impl fmt::Display for Chunk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_with(f, &ChartDisplayOptions::default())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::chordpro::{charts::Chart, parser::set_extensions_enabled};
+    use crate::{
+        chordpro::{
+            builder::ChartBuilder,
+            charts::{
+                CapoTransposition, Chart, ChartDisplayOptions, Chunk, KeyError, Line, LineCol,
+                LineEnding, sort_by_title,
+            },
+            directives::Directive,
+            parser::ChartParseOptions,
+        },
+        theory::{
+            chords::Chord,
+            notes::{Accidental, Letter::*, NoteNaming, Spelling},
+            scales::{Mode, Scale, ScaleDegree},
+        },
+    };
 
     const O_HOLY_NIGHT: &str = include_str!("../../examples/O-Holy-Night-.chordpro");
     const O_HOLY_NIGHT_BFLAT: &str = include_str!("../../examples/O-Holy-Night-Bb.chordpro");
+    const HOW_GREAT_THOU_ART: &str =
+        include_str!("../../examples/How-Great-Thou-Art-(Whakaaria-Mai).chordpro");
 
     #[test]
     fn test_transpose() {
-        set_extensions_enabled(true);
-        let mut chart = O_HOLY_NIGHT.parse::<Chart>().unwrap();
-        chart.transpose_to("Bb".parse().unwrap());
+        let mut chart =
+            Chart::parse_with(O_HOLY_NIGHT, &ChartParseOptions::with_extensions()).unwrap();
+        chart.transpose_to("Bb".parse().unwrap()).unwrap();
         assert_eq!(format!("{chart}"), O_HOLY_NIGHT_BFLAT);
     }
+
+    #[test]
+    fn test_transpose_by_large_intervals() {
+        let chart = "{key:G}\n[G]Lorem [B7]ipsum [Cb]dolor"
+            .parse::<Chart>()
+            .unwrap();
+
+        for (semitones, expected) in [
+            (-127, "{key:C}\n[C]Lorem [E7]ipsum [E]dolor\n"),
+            (127, "{key:D}\n[D]Lorem [F#7]ipsum [F#]dolor\n"),
+            (24, "{key:G}\n[G]Lorem [B7]ipsum [B]dolor\n"),
+        ] {
+            let mut transposed = chart.clone();
+            transposed.transpose_by(semitones).unwrap();
+            assert_eq!(transposed.to_string(), expected);
+        }
+
+        let mut chart = "{key:Gb}\n[Gb/C##]Lorem [Fb/B#]ipsum"
+            .parse::<Chart>()
+            .unwrap();
+        chart.transpose_by(1).unwrap();
+        assert_eq!(chart.to_string(), "{key:G}\n[G/D#]Lorem [F/B##]ipsum\n");
+    }
+
+    #[test]
+    fn test_chord_histogram() {
+        let chart = Chart::parse_with(O_HOLY_NIGHT, &ChartParseOptions::with_extensions()).unwrap();
+        let histogram = chart.chord_histogram();
+
+        assert_eq!(histogram[&Chord::major(G.natural())], 17);
+        assert_eq!(histogram[&Chord::minor(E.natural())], 14);
+        assert_eq!(histogram.values().sum::<usize>(), chart.chords().count());
+
+        // G/D is counted with G, as only the root is considered.
+        let degrees = chart.scale_degree_histogram(Scale(G.natural()));
+        assert_eq!(degrees[&ScaleDegree::new(1, Accidental::NATURAL)], 18);
+        assert_eq!(degrees[&ScaleDegree::new(6, Accidental::NATURAL)], 14);
+    }
+
+    #[test]
+    fn test_non_diatonic_chords() {
+        let chart = "{key:C}\n[C]Lorem [Am]ipsum\n[D7]dolor [G7]sit\n[C/E]amet [Bb]consectetur"
+            .parse::<Chart>()
+            .unwrap();
+        let found = chart
+            .non_diatonic_chords(Scale(C.natural()))
+            .into_iter()
+            .map(|(position, chord)| (position.map(|p| p.line), chord.to_string()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            found,
+            [(Some(3), "D7".to_owned()), (Some(4), "Bb".to_owned())]
+        );
+
+        let built = ChartBuilder::new()
+            .chord_over(Chord::major(C.natural()), "Lorem ")
+            .chord_over(Chord::major(E.natural()), "ipsum")
+            .build();
+        assert_eq!(
+            built.non_diatonic_chords(Scale(C.natural())),
+            [(None, Chord::major(E.natural()))]
+        );
+
+        let numbers = "[1]Lorem [2]ipsum [b7]dolor [5/7]sit"
+            .parse::<Chart>()
+            .unwrap();
+        let found = numbers.non_diatonic_chords(Scale(G.natural()));
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].1.to_string(), "2");
+        assert_eq!(found[1].1.to_string(), "b7");
+    }
+
+    #[test]
+    fn test_simplify_accidentals() {
+        let mut chart = "{key:C}\n[C]Lorem [D#dim]ipsum [E/G#]dolor"
+            .parse::<Chart>()
+            .unwrap();
+        chart.transpose_to(Scale(C.sharp())).unwrap();
+        assert_eq!(
+            chart.to_string(),
+            "{key:C#}\n[C#]Lorem [D##dim]ipsum [E#/G##]dolor\n"
+        );
+
+        chart.simplify_accidentals(Scale(C.sharp()));
+        assert_eq!(
+            chart.to_string(),
+            "{key:C#}\n[C#]Lorem [Edim]ipsum [E#/A]dolor\n"
+        );
+
+        let mut chart = "[Gb]Lorem [Bbb]ipsum [Cb]dolor [Fb]sit [F##]amet"
+            .parse::<Chart>()
+            .unwrap();
+        chart.simplify_accidentals(Scale(G.flat()));
+        assert_eq!(
+            chart.to_string(),
+            "[Gb]Lorem [A]ipsum [Cb]dolor [E]sit [G]amet\n"
+        );
+        chart.simplify_accidentals(Scale(C.natural()));
+        assert_eq!(
+            chart.to_string(),
+            "[Gb]Lorem [A]ipsum [B]dolor [E]sit [G]amet\n"
+        );
+    }
+
+    #[test]
+    fn test_set_title() {
+        let mut chart = "{key:G}\n[G]Lorem".parse::<Chart>().unwrap();
+        chart.set_title("Ipsum");
+        assert_eq!(chart.to_string(), "{title:Ipsum}\n{key:G}\n[G]Lorem\n");
+
+        let mut chart = "{artist:Dolor}\n{title:Lorem}\n[G]Lorem"
+            .parse::<Chart>()
+            .unwrap();
+        chart.set_title("Ipsum");
+        assert_eq!(
+            chart.to_string(),
+            "{artist:Dolor}\n{title:Ipsum}\n[G]Lorem\n"
+        );
+    }
+
+    #[test]
+    fn test_set_tempo_and_remove_directive() {
+        let mut chart = "{title:Lorem}\n[G]Ipsum".parse::<Chart>().unwrap();
+        chart.set_tempo(90);
+        assert_eq!(chart.to_string(), "{title:Lorem}\n{tempo:90}\n[G]Ipsum\n");
+
+        chart.set_tempo(120);
+        assert_eq!(chart.tempo(), Some(120));
+        chart.insert_line(1, Line::Directive(Directive::Capo(2)));
+        chart.remove_directive(|directive| matches!(directive, Directive::Tempo { .. }));
+        assert_eq!(chart.to_string(), "{title:Lorem}\n{capo:2}\n[G]Ipsum\n");
+    }
+
+    #[test]
+    fn test_replace_chords_from() {
+        let mut line = "[1]Lorem ipsum [4]dolor".parse::<Chart>().unwrap().lines[0].clone();
+        line.replace_chords_from("5     6m          1  4").unwrap();
+
+        assert_eq!(
+            line,
+            Line::Content {
+                chunks: vec![
+                    Chunk {
+                        chord: Some(Chord::major(5)),
+                        lyrics: "Lorem ".to_owned()
+                    },
+                    Chunk {
+                        chord: Some(Chord::minor(6)),
+                        lyrics: "ipsum dolor".to_owned()
+                    },
+                    Chunk {
+                        chord: Some(Chord::major(1)),
+                        lyrics: "".to_owned()
+                    },
+                    Chunk {
+                        chord: Some(Chord::major(4)),
+                        lyrics: "".to_owned()
+                    },
+                ],
+                inline: true,
+            }
+        );
+
+        line.replace_chords_from("").unwrap();
+        assert_eq!(line.to_string(), "Lorem ipsum dolor");
+        assert!(line.replace_chords_from("Lorem").is_err());
+    }
+
+    #[test]
+    fn test_sort_by_title() {
+        let mut charts = [
+            "{title:The Rock}\n{sorttitle:Rock, The}",
+            "{title:Amazing Grace}",
+            "{title:Silent Night}",
+        ]
+        .map(|input| input.parse::<Chart>().unwrap());
+        sort_by_title(&mut charts);
+
+        let titles = charts
+            .iter()
+            .map(|c| c.title().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, vec!["Amazing Grace", "The Rock", "Silent Night"]);
+    }
+
+    #[test]
+    fn test_quantize_chords() {
+        let mut chart =
+            Chart::parse_with(O_HOLY_NIGHT, &ChartParseOptions::with_extensions()).unwrap();
+        assert_eq!(
+            chart.lines[13].to_string(),
+            "G      D         C                   Em\nO holy night the stars are brightly shining"
+        );
+
+        chart.quantize_chords();
+        assert_eq!(
+            chart.lines[13].to_string(),
+            "G      D         C                  Em\nO holy night the stars are brightly shining"
+        );
+        assert_eq!(
+            chart.lines[13],
+            Line::Content {
+                chunks: vec![
+                    Chunk {
+                        chord: Some("G".parse().unwrap()),
+                        lyrics: "O holy ".to_owned()
+                    },
+                    Chunk {
+                        chord: Some("D".parse().unwrap()),
+                        lyrics: "night the ".to_owned()
+                    },
+                    Chunk {
+                        chord: Some("C".parse().unwrap()),
+                        lyrics: "stars are brightly ".to_owned()
+                    },
+                    Chunk {
+                        chord: Some("Em".parse().unwrap()),
+                        lyrics: "shining".to_owned()
+                    },
+                ],
+                inline: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        let chunk = |chord: Option<&str>, lyrics: &str| Chunk {
+            chord: chord.map(|chord| chord.parse().unwrap()),
+            lyrics: lyrics.to_owned(),
+        };
+        let mut chart = Chart {
+            lines: vec![Line::Content {
+                chunks: vec![
+                    chunk(None, "Lorem "),
+                    chunk(None, ""),
+                    chunk(None, "ipsum "),
+                    chunk(Some("G"), ""),
+                    chunk(None, "dolor "),
+                    chunk(None, "sit "),
+                    chunk(Some("C"), "amet"),
+                    chunk(None, ""),
+                ],
+                inline: true,
+            }],
+            ..Default::default()
+        };
+        chart.normalize();
+
+        assert_eq!(
+            chart.lines[0],
+            Line::Content {
+                chunks: vec![
+                    chunk(None, "Lorem ipsum "),
+                    chunk(Some("G"), ""),
+                    chunk(None, "dolor sit "),
+                    chunk(Some("C"), "amet"),
+                ],
+                inline: true,
+            }
+        );
+        assert_eq!(chart.to_string(), "Lorem ipsum [G]dolor sit [C]amet\n");
+    }
+
+    #[test]
+    fn test_declared_keyless() {
+        let mut chart = "{key: none}\n[1]Lorem".parse::<Chart>().unwrap();
+        assert_eq!(chart.key(), None);
+        assert!(chart.is_declared_keyless());
+        assert_eq!(chart.to_numbers(), Err(KeyError::DeclaredKeyless));
+        assert_eq!(
+            chart.transpose_to("C".parse().unwrap()),
+            Err(KeyError::DeclaredKeyless)
+        );
+        assert_eq!(chart.to_string(), "{key:none}\n[1]Lorem\n");
+
+        let mut chart = "[1]Lorem".parse::<Chart>().unwrap();
+        assert_eq!(chart.to_numbers(), Err(KeyError::Missing));
+    }
+
+    #[test]
+    fn test_meta_key_and_tempo() {
+        let mut chart = "{meta: key G}\n{meta: tempo 84}\n[G]Lorem [D]ipsum"
+            .parse::<Chart>()
+            .unwrap();
+        assert_eq!(chart.key(), Some("G".parse().unwrap()));
+        assert_eq!(chart.tempo(), Some(84));
+
+        chart.transpose_to("Bb".parse().unwrap()).unwrap();
+        assert_eq!(
+            chart.to_string(),
+            "{meta:key Bb}\n{meta:tempo 84}\n[Bb]Lorem [F]ipsum\n"
+        );
+    }
+
+    #[test]
+    fn test_meta() {
+        let chart =
+            "{meta: year 2020}\n{ccli: 7195204}\n{meta: ccli 123}\n{meta: copyright Lorem Ipsum}"
+                .parse::<Chart>()
+                .unwrap();
+
+        assert_eq!(chart.meta("year"), Some("2020"));
+        assert_eq!(chart.meta("ccli"), Some("123"));
+        assert_eq!(chart.meta("copyright"), Some("Lorem Ipsum"));
+        assert_eq!(chart.meta("album"), None);
+        assert!(matches!(
+            &chart.lines[1],
+            Line::Directive(Directive::Other(content)) if content == "ccli: 7195204"
+        ));
+        assert_eq!(
+            chart.to_string(),
+            "{meta:year 2020}\n{ccli: 7195204}\n{meta:ccli 123}\n{meta:copyright Lorem Ipsum}\n"
+        );
+
+        let chart = HOW_GREAT_THOU_ART.parse::<Chart>().unwrap();
+        assert_eq!(chart.meta("ccli"), Some("7195204"));
+    }
+
+    #[test]
+    fn test_to_changes_only() {
+        let chart = "[G]Lorem [D]ipsum\n\n[G]dolor [C]sit\n[G]amet [C]consectetur [D]adipiscing"
+            .parse::<Chart>()
+            .unwrap();
+        assert_eq!(
+            chart
+                .content_lines()
+                .map(<[Chunk]>::len)
+                .collect::<Vec<_>>(),
+            vec![2, 0, 2, 3]
+        );
+        assert_eq!(
+            chart.to_changes_only(),
+            "[G]Lorem [D]ipsum\n\ndolor [C]sit\namet consectetur [D]adipiscing\n"
+        );
+    }
+
+    #[test]
+    fn test_parallel() {
+        let english = Chart::parse_with(
+            "{title:English}\nG       C\nThen sings my soul",
+            &ChartParseOptions::with_extensions(),
+        );
+        let english = english.unwrap();
+        let maori = "{title:Māori}\n[G]Whakaaria [C]mai\n[D]Tōu rīwai"
+            .parse::<Chart>()
+            .unwrap();
+
+        let widths = english
+            .parallel(&maori)
+            .map(|(a, b)| (a.map(Line::alignment_width), b.map(Line::alignment_width)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            widths,
+            vec![(Some(15), Some(13)), (Some(18), Some(19)), (None, Some(12)),]
+        );
+    }
+
+    #[test]
+    fn test_apply_capo() {
+        let mut chart = "{key:A}\n{capo: 2}\n[A]Lorem [E/G#]ipsum [3m]dolor"
+            .parse::<Chart>()
+            .unwrap();
+        assert_eq!(chart.capo(), Some(2));
+        assert_eq!(
+            chart.to_string(),
+            "{key:A}\n{capo:2}\n[A]Lorem [E/G#]ipsum [3m]dolor\n"
+        );
+
+        chart.apply_capo();
+        assert_eq!(chart.capo(), None);
+        assert_eq!(
+            chart.to_string(),
+            "{key:A}\n[G]Lorem [D/F#]ipsum [3m]dolor\n"
+        );
+        assert_eq!(chart.position(1), Some(LineCol { line: 3, column: 1 }));
+
+        let mut chart = "{capo:1}\n[E]Lorem".parse::<Chart>().unwrap();
+        chart.apply_capo();
+        assert_eq!(chart.to_string(), "[Eb]Lorem\n");
+    }
+
+    #[test]
+    fn test_transpose_with_capo() {
+        let chart = "{key:A}\n{capo:2}\n[A]Lorem [E/G#]ipsum"
+            .parse::<Chart>()
+            .unwrap();
+
+        let mut keep_shapes = chart.clone();
+        keep_shapes
+            .transpose_with_capo(Scale(B.flat()), CapoTransposition::KeepShapes)
+            .unwrap();
+        assert_eq!(
+            keep_shapes.to_string(),
+            "{key:Bb}\n{capo:3}\n[Bb]Lorem [F/A]ipsum\n"
+        );
+        // The shapes played are still G and D/F#.
+        let mut shapes = keep_shapes.clone();
+        shapes.apply_capo();
+        assert_eq!(shapes.to_string(), "{key:Bb}\n[G]Lorem [D/F#]ipsum\n");
+
+        let mut down = chart.clone();
+        down.transpose_with_capo(Scale(G.natural()), CapoTransposition::KeepShapes)
+            .unwrap();
+        assert_eq!(down.to_string(), "{key:G}\n[G]Lorem [D/F#]ipsum\n");
+        assert_eq!(down.position(1), Some(LineCol { line: 3, column: 1 }));
+
+        let mut concert = chart.clone();
+        concert
+            .transpose_with_capo(Scale(B.flat()), CapoTransposition::ConcertPitch)
+            .unwrap();
+        assert_eq!(concert.to_string(), "{key:Bb}\n[Bb]Lorem [F/A]ipsum\n");
+    }
+
+    #[test]
+    fn test_display_wide_characters() {
+        let chart = Chart::parse_with(
+            "   F      Bb\nWhakaaria mai\n  C   G\nÉté 夏天 café",
+            &ChartParseOptions::with_extensions(),
+        );
+        let mut chart = chart.unwrap();
+
+        assert_eq!(chart.lines[1].to_string(), "  C   G\nÉté 夏天 café");
+        chart.set_inline(true);
+        assert_eq!(chart.lines[1].to_string(), "Ét[C]é 夏[G]天 café");
+    }
+
+    #[test]
+    fn test_escaped_brackets() {
+        let input = "[G]Lorem \\[spoken\\] [C]ipsum\n";
+        let mut chart = input.parse::<Chart>().unwrap();
+
+        assert_eq!(chart.chords().count(), 2);
+        assert_eq!(chart.lyrics_only(), "Lorem [spoken] ipsum\n");
+        assert_eq!(chart.to_string(), input);
+        chart.set_inline(false);
+        assert_eq!(
+            chart.to_string(),
+            "G              C\nLorem [spoken] ipsum\n"
+        );
+    }
+
+    #[test]
+    fn test_escaped_brackets_chords_above_round_trip() {
+        let mut chart = "Lorem \\[spoken\\] \\\\ ipsum\n[G]Dolor [C]sit\n"
+            .parse::<Chart>()
+            .unwrap();
+        chart.set_inline(false);
+        let output = chart.to_string();
+
+        assert_eq!(
+            output,
+            "Lorem \\[spoken\\] \\\\ ipsum\nG     C\nDolor sit\n"
+        );
+        let mut reparsed =
+            Chart::parse_with(&output, &ChartParseOptions::with_extensions()).unwrap();
+        reparsed.set_inline(false);
+        assert_eq!(reparsed, chart);
+    }
+
+    #[test]
+    fn test_display_german_names() {
+        let input = "{key:H}\n[H]Lorem [B/D]ipsum\nG     C\nDolor sit\n";
+        let options = ChartParseOptions {
+            extensions: true,
+            note_naming: NoteNaming::German,
+            ..Default::default()
+        };
+        let mut chart = Chart::parse_with(input, &options).unwrap();
+        let german = ChartDisplayOptions {
+            note_naming: NoteNaming::German,
+            ..Default::default()
+        };
+
+        assert_eq!(chart.key(), Some(Scale(B.natural())));
+        assert_eq!(
+            chart.to_string(),
+            "{key:B}\n[B]Lorem [Bb/D]ipsum\nG     C\nDolor sit\n"
+        );
+        assert_eq!(chart.display_with(german.clone()).to_string(), input);
+        chart.transpose_to(Scale(A.natural())).unwrap();
+        assert_eq!(
+            chart.display_with(german).to_string(),
+            "{key:A}\n[A]Lorem [Ab/C]ipsum\nF     B\nDolor sit\n"
+        );
+    }
+
+    #[test]
+    fn test_escaped_backslash() {
+        let input = "[G]Lorem \\\\\\[spoken\\] ipsum \\ dolor\n";
+        let chart = input.parse::<Chart>().unwrap();
+
+        assert_eq!(chart.lyrics_only(), "Lorem \\[spoken] ipsum \\ dolor\n");
+        assert_eq!(
+            chart.to_string(),
+            "[G]Lorem \\\\\\[spoken\\] ipsum \\\\ dolor\n"
+        );
+    }
+
+    #[test]
+    fn test_escaped_brackets_over_lyrics() {
+        let chart = Chart::parse_with(
+            "G     C\nLorem \\[spoken\\] ipsum",
+            &ChartParseOptions::with_extensions(),
+        )
+        .unwrap();
+
+        assert_eq!(chart.lyrics_only(), "Lorem [spoken] ipsum\n");
+        let mut inline = chart.clone();
+        inline.set_inline(true);
+        assert_eq!(inline.to_string(), "[G]Lorem [C]\\[spoken\\] ipsum\n");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let chart = Chart::parse_with(O_HOLY_NIGHT, &ChartParseOptions::with_extensions());
+        let chart = chart.unwrap();
+
+        let json = serde_json::to_string(&chart).unwrap();
+        assert!(json.contains(
+            r#"{"chord":{"root":{"Letter":["G",0]},"quality":"","bass":null},"lyrics":"O holy "}"#
+        ));
+        assert_eq!(serde_json::from_str::<Chart>(&json).unwrap(), chart);
+    }
+
+    #[test]
+    fn test_to_numbers_in_minor() {
+        let mut chart = "{key:Am}\n[Am]Lorem [F]ipsum [G]dolor [Am]sit [E]amet"
+            .parse::<Chart>()
+            .unwrap();
+        chart.to_numbers_in_mode(Mode::NaturalMinor).unwrap();
+        assert_eq!(
+            chart.to_string(),
+            "{key:A}\n[1m]Lorem [6]ipsum [7]dolor [1m]sit [5]amet\n"
+        );
+    }
+
+    #[test]
+    fn test_transpose_with_spelling() {
+        let chart = "{key:C}\n[C]Lorem [Eb]ipsum [F]dolor [G#dim]sit [B]amet"
+            .parse::<Chart>()
+            .unwrap();
+
+        let mut follow_key = chart.clone();
+        follow_key.transpose_to("Gb".parse().unwrap()).unwrap();
+        assert_eq!(
+            follow_key.to_string(),
+            "{key:Gb}\n[Gb]Lorem [Bbb]ipsum [Cb]dolor [Ddim]sit [F]amet\n"
+        );
+
+        let mut flats = chart.clone();
+        flats
+            .transpose_to_with_spelling("Gb".parse().unwrap(), Spelling::Flats)
+            .unwrap();
+        assert_eq!(
+            flats.to_string(),
+            "{key:Gb}\n[Gb]Lorem [A]ipsum [B]dolor [Ddim]sit [F]amet\n"
+        );
+        assert!(!flats.to_string().contains("bb"));
+
+        let mut sharps = chart;
+        sharps
+            .transpose_to_with_spelling("Gb".parse().unwrap(), Spelling::Sharps)
+            .unwrap();
+        assert_eq!(
+            sharps.to_string(),
+            "{key:Gb}\n[F#]Lorem [A]ipsum [B]dolor [Ddim]sit [F]amet\n"
+        );
+    }
+
+    #[test]
+    fn test_chords() {
+        let chart = Chart::parse_with(O_HOLY_NIGHT, &ChartParseOptions::with_extensions());
+        let chart = chart.unwrap();
+
+        assert_eq!(
+            chart
+                .chords()
+                .take(6)
+                .map(Chord::to_string)
+                .collect::<Vec<_>>(),
+            vec!["G", "D", "Em", "C", "G", "D"]
+        );
+        assert_eq!(
+            chart
+                .unique_chords()
+                .iter()
+                .map(Chord::to_string)
+                .collect::<Vec<_>>(),
+            vec!["G", "D", "Em", "C", "Bm", "Am", "D/C", "G/D"]
+        );
+    }
+
+    #[test]
+    fn test_detect_key() {
+        for (source, key) in [
+            (O_HOLY_NIGHT, "G"),
+            (O_HOLY_NIGHT_BFLAT, "Bb"),
+            (HOW_GREAT_THOU_ART, "Bb"),
+        ] {
+            let chart = Chart::parse_with(source, &ChartParseOptions::with_extensions()).unwrap();
+            assert_eq!(chart.detect_key(), Some(key.parse().unwrap()));
+        }
+
+        let mut chart = "[G]Lorem [Em]ipsum [C]dolor [D]sit"
+            .parse::<Chart>()
+            .unwrap();
+        assert_eq!(chart.detect_key(), Some("G".parse().unwrap()));
+        chart.to_numbers().unwrap();
+        assert_eq!(chart.to_string(), "[1]Lorem [6m]ipsum [4]dolor [5]sit\n");
+
+        let chart = "[1]Lorem".parse::<Chart>().unwrap();
+        assert_eq!(chart.detect_key(), None);
+    }
+
+    #[test]
+    fn test_display_with_options() {
+        let mut chart = "[G]A[Dsus4]b[C]cd".parse::<Chart>().unwrap();
+        chart.set_inline(false);
+
+        let wide = ChartDisplayOptions {
+            min_gap: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            chart.display_with(wide).to_string(),
+            "G  Dsus4  C\nA  b      cd\n"
+        );
+
+        let unpadded = ChartDisplayOptions {
+            pad_lyrics: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            chart.display_with(unpadded).to_string(),
+            "G Dsus4 C\nAbcd\n"
+        );
+
+        assert_eq!(
+            chart.display_with(Default::default()).to_string(),
+            chart.to_string()
+        );
+    }
+
+    #[test]
+    fn test_display_left_margin() {
+        let mut chart = Chart::parse_with(
+            "{key:G}\nG     C\nLorem ipsum\n\nD\n",
+            &ChartParseOptions::with_extensions(),
+        )
+        .unwrap();
+        let indented = ChartDisplayOptions {
+            left_margin: 4,
+            ..Default::default()
+        };
+        assert_eq!(
+            chart.display_with(indented.clone()).to_string(),
+            "{key:G}\n    G     C\n    Lorem ipsum\n\n    D\n\n"
+        );
+
+        chart.transpose_by(-1).unwrap();
+        assert_eq!(
+            chart.display_with(indented).to_string(),
+            "{key:Gb}\n    Gb    Cb\n    Lorem ipsum\n\n    Db\n\n"
+        );
+    }
+
+    #[test]
+    fn test_display_line_ending() {
+        let input = "{title:Lorem}\r\n[G]Lorem ipsum\r\n\r\nG     C\r\nDolor sit\r\n";
+        let chart = Chart::parse_with(input, &ChartParseOptions::with_extensions()).unwrap();
+        let display = |line_ending| {
+            chart
+                .display_with(ChartDisplayOptions {
+                    line_ending,
+                    ..Default::default()
+                })
+                .to_string()
+        };
+
+        assert_eq!(chart.line_ending(), LineEnding::CrLf);
+        assert_eq!(display(LineEnding::Detect), input);
+        assert_eq!(display(LineEnding::Lf), input.replace('\r', ""));
+        assert_eq!(chart.to_string(), input.replace('\r', ""));
+
+        let chart = "[G]Lorem\n[C]ipsum\r\n".parse::<Chart>().unwrap();
+        assert_eq!(chart.line_ending(), LineEnding::Lf);
+        assert_eq!(
+            chart
+                .display_with(ChartDisplayOptions {
+                    line_ending: LineEnding::CrLf,
+                    ..Default::default()
+                })
+                .to_string(),
+            "[G]Lorem\r\n[C]ipsum\r\n"
+        );
+    }
+
+    #[test]
+    fn test_display_chords_below() {
+        let chart = Chart::parse_with(
+            "G     C\nLorem ipsum\nDolor sit\n",
+            &ChartParseOptions::with_extensions(),
+        )
+        .unwrap();
+        let below = ChartDisplayOptions {
+            chords_below: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            chart.display_with(below.clone()).to_string(),
+            "Lorem ipsum\nG     C\nDolor sit\n"
+        );
+
+        let mut chart = "[G][D]\nLorem".parse::<Chart>().unwrap();
+        chart.set_inline(false);
+        assert_eq!(chart.display_with(below).to_string(), "G D\nLorem\n");
+    }
+
+    #[test]
+    fn test_display_chord_separator() {
+        let mut chart = "[G][D][C]Lorem".parse::<Chart>().unwrap();
+        chart.set_inline(false);
+        assert_eq!(chart.to_string(), "G D C\nLorem\n");
+        chart.set_inline(true);
+        let separated = ChartDisplayOptions {
+            chord_separator: " ".into(),
+            ..Default::default()
+        };
+
+        assert_eq!(chart.to_string(), "[G][D][C]Lorem\n");
+        assert_eq!(
+            chart.display_with(separated.clone()).to_string(),
+            "[G] [D] [C]Lorem\n"
+        );
+
+        let chart = "[G]Lorem[D]ipsum".parse::<Chart>().unwrap();
+        assert_eq!(
+            chart.display_with(separated).to_string(),
+            "[G]Lorem[D]ipsum\n"
+        );
+    }
+
+    #[test]
+    fn test_set_inline_range() {
+        let mut chart = Chart::parse_with(
+            "{key:G}\nG     D\nLorem ipsum\nEm    C\nDolor sit\nG\nAmet",
+            &ChartParseOptions::with_extensions(),
+        )
+        .unwrap();
+        chart.set_inline_range(..3, true);
+        chart.transpose_by(2).unwrap();
+
+        assert_eq!(
+            chart.to_string(),
+            "{key:A}\n[A]Lorem [E]ipsum\n[F#m]Dolor [D]sit\nA\nAmet\n"
+        );
+    }
+
+    #[test]
+    fn test_back_to_back_chords() {
+        let input = "[C][G]word and [Am]more\n";
+        let mut chart = input.parse::<Chart>().unwrap();
+        assert_eq!(chart.to_string(), input);
+
+        chart.set_inline(false);
+        assert_eq!(chart.to_string(), "C G      Am\nword and more\n");
+    }
 }
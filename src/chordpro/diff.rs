@@ -0,0 +1,160 @@
+use crate::{
+    chordpro::charts::{Chart, Chunk, Line},
+    theory::{
+        chords::Chord,
+        notes::{Letter, Note},
+        scales::Scale,
+    },
+};
+
+/// A chord that differs between two charts. `before` is `None` where a chord
+/// was added, and `after` is `None` where one was removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChordDiff {
+    /// The index of the line, counting only lyric and chord lines.
+    pub line: usize,
+    /// The index of the chunk in the line.
+    pub chunk: usize,
+    pub before: Option<Chord>,
+    pub after: Option<Chord>,
+}
+
+impl Chart {
+    /// Lists the chords that differ between this chart and `other`. Lyric and
+    /// chord lines are paired up in order, ignoring directives, and within a
+    /// line the chunks are paired up in order. Lyrics are not compared. When
+    /// one chart has more lines or chunks than the other, the chords in the
+    /// extra ones are reported as added or removed.
+    ///
+    /// Chords that are only spelled differently, such as C# and Db or Cmaj7
+    /// and CM7, are not reported. Numbered chords are compared with lettered
+    /// ones in this chart's key, and are always reported as changed if there
+    /// is no key.
+    pub fn diff(&self, other: &Chart) -> Vec<ChordDiff> {
+        let key = self.key().or_else(|| self.detect_key());
+        let same = |before: &Option<Chord>, after: &Option<Chord>| match (before, after) {
+            (Some(before), Some(after)) => {
+                // Two letters, or two numbers, are the same distance apart in
+                // any key.
+                let key = key.or_else(|| {
+                    same_kind_of_notes(before, after).then_some(Scale(Letter::C.natural()))
+                });
+                key.is_some_and(|key| before.enharmonic_eq(after, key))
+            }
+            _ => before == after,
+        };
+        let before = content_lines(self);
+        let after = content_lines(other);
+
+        let mut diffs = Vec::new();
+        for line in 0..before.len().max(after.len()) {
+            let before = before.get(line).copied().unwrap_or_default();
+            let after = after.get(line).copied().unwrap_or_default();
+            for chunk in 0..before.len().max(after.len()) {
+                let before = before.get(chunk).and_then(|chunk| chunk.chord.clone());
+                let after = after.get(chunk).and_then(|chunk| chunk.chord.clone());
+                if !same(&before, &after) {
+                    diffs.push(ChordDiff {
+                        line,
+                        chunk,
+                        before,
+                        after,
+                    });
+                }
+            }
+        }
+        diffs
+    }
+}
+
+/// Whether the roots of the two chords, and their bass notes, are either both
+/// letters or both numbers.
+fn same_kind_of_notes(a: &Chord, b: &Chord) -> bool {
+    let is_number = |note: Note| matches!(note, Note::Number(_));
+    is_number(a.root) == is_number(b.root)
+        && a.bass.map(is_number).unwrap_or_default() == b.bass.map(is_number).unwrap_or_default()
+}
+
+fn content_lines(chart: &Chart) -> Vec<&[Chunk]> {
+    chart
+        .lines
+        .iter()
+        .filter_map(|line| match line {
+            Line::Content { chunks, .. } => Some(chunks.as_slice()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chordpro::{charts::Chart, parser::ChartParseOptions};
+
+    const O_HOLY_NIGHT: &str = include_str!("../../examples/O-Holy-Night-.chordpro");
+    const O_HOLY_NIGHT_BFLAT: &str = include_str!("../../examples/O-Holy-Night-Bb.chordpro");
+
+    #[test]
+    fn test_diff_transposition() {
+        let chart = Chart::parse_with(O_HOLY_NIGHT, &ChartParseOptions::with_extensions()).unwrap();
+        let transposed =
+            Chart::parse_with(O_HOLY_NIGHT_BFLAT, &ChartParseOptions::with_extensions()).unwrap();
+        let diffs = chart.diff(&transposed);
+
+        assert_eq!(diffs.len(), chart.chords().count());
+        assert!(
+            diffs
+                .iter()
+                .all(|diff| diff.before.is_some() && diff.after.is_some())
+        );
+        assert!(chart.diff(&chart).is_empty());
+    }
+
+    #[test]
+    fn test_diff_added_and_removed() {
+        let before = "[G]Lorem [C]ipsum\n{comment:Dolor}\n[D]sit"
+            .parse::<Chart>()
+            .unwrap();
+        let after = "[G]Lorem ipsum [Am]dolor\n[D]sit\n[G]amet"
+            .parse::<Chart>()
+            .unwrap();
+        let diffs = before.diff(&after);
+
+        let summary = diffs
+            .iter()
+            .map(|diff| {
+                (
+                    diff.line,
+                    diff.chunk,
+                    diff.before.as_ref().map(ToString::to_string),
+                    diff.after.as_ref().map(ToString::to_string),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (0, 1, Some("C".to_owned()), Some("Am".to_owned())),
+                (2, 0, None, Some("G".to_owned())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_respelling() {
+        let before = "{key:E}\n[C#m]Lorem [4]ipsum".parse::<Chart>().unwrap();
+        let after = "{key:E}\n[Dbm]Lorem [A]ipsum".parse::<Chart>().unwrap();
+
+        assert!(before.diff(&after).is_empty());
+    }
+
+    #[test]
+    fn test_diff_respelling_without_key() {
+        let before = "[1maj7]Lorem [b3]ipsum [4]dolor".parse::<Chart>().unwrap();
+        let after = "[1M7]Lorem [#2]ipsum [F]dolor".parse::<Chart>().unwrap();
+        assert_eq!(before.detect_key(), None);
+        let diffs = before.diff(&after);
+
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].chunk, 2);
+    }
+}
@@ -1,24 +1,188 @@
 use std::fmt;
 
-use crate::theory::scales::Scale;
+use crate::theory::{instruments::Instrument, scales::Scale};
 
+/// A `{name: value}` line. Text values are stored without the spaces around
+/// them, so `{title: O Holy Night }` has the title `"O Holy Night"`.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Directive {
     Title(String),
-    Comment(String),
+    SortTitle(String),
+    Subtitle(String),
+    Artist(String),
+    Composer(String),
+    Album(String),
+    /// `{comment}`, `{comment_italic}` or `{comment_box}`.
+    Comment {
+        text: String,
+        style: CommentStyle,
+    },
     Key(Scale),
-    Tempo(u32),
+    /// `{key: none}`, declaring that the song deliberately has no key.
+    NoKey,
+    /// `{tempo}` with the beats per minute and, if the value was more than a
+    /// bare number (e.g. `76 (moderate)` or `q=120`), the original text.
+    Tempo {
+        bpm: u32,
+        text: Option<String>,
+    },
+    Time {
+        beats: u8,
+        unit: u8,
+    },
+    Capo(u8),
+    Meta {
+        name: String,
+        value: String,
+    },
+    Instrument(Instrument),
+    Define(ChordDefinition),
+    /// `{chord}` with a shape, the shorthand for `{define}`.
+    Chord(ChordDefinition),
+    SectionStart {
+        kind: SectionKind,
+        label: Option<String>,
+    },
+    SectionEnd {
+        kind: SectionKind,
+    },
+    /// `{columns: N}`, the number of columns to print the chart in.
+    Columns(u8),
+    /// `{column_break}`, which starts a new column when printing.
+    ColumnBreak,
+    /// `{new_page}`, or `{new_physical_page}` if `physical`, which starts a
+    /// new page when printing.
+    PageBreak {
+        physical: bool,
+    },
+    /// `{new_song}`, which separates songs in a songbook.
+    NewSong,
+    /// `{chorus}`, which repeats the most recent chorus.
+    ChorusRecall,
     Other(String),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SectionKind {
+    Chorus,
+    Verse,
+    Bridge,
+    Tab,
+    Grid,
+}
+
+/// How a comment is set apart from the lyrics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CommentStyle {
+    #[default]
+    Plain,
+    Italic,
+    Box,
+}
+
+/// A chord shape, as given by `{define}` or the `{chord}` shorthand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChordDefinition {
+    pub name: String,
+    /// The fret for each string, from lowest to highest. `None` is a muted
+    /// string.
+    pub frets: Vec<Option<u8>>,
+    /// The finger for each string, where `0` is no finger.
+    pub fingers: Option<Vec<u8>>,
+    /// Whether the frets, and any fingers, were written as a single string
+    /// such as `x02210` rather than separated by spaces.
+    pub compact: bool,
+}
+
 impl fmt::Display for Directive {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Directive::Title(title) => write!(f, "{{title:{title}}}"),
-            Directive::Comment(comment) => write!(f, "{{comment:{comment}}}"),
+            Directive::SortTitle(title) => write!(f, "{{sorttitle:{title}}}"),
+            Directive::Subtitle(subtitle) => write!(f, "{{subtitle:{subtitle}}}"),
+            Directive::Artist(artist) => write!(f, "{{artist:{artist}}}"),
+            Directive::Composer(composer) => write!(f, "{{composer:{composer}}}"),
+            Directive::Album(album) => write!(f, "{{album:{album}}}"),
+            Directive::Comment { text, style } => write!(f, "{{{style}:{text}}}"),
             Directive::Key(scale) => write!(f, "{{key:{scale}}}"),
-            Directive::Tempo(tempo) => write!(f, "{{tempo:{tempo}}}"),
+            Directive::NoKey => write!(f, "{{key:none}}"),
+            Directive::Tempo {
+                text: Some(text), ..
+            } => write!(f, "{{tempo:{text}}}"),
+            Directive::Tempo { bpm, text: None } => write!(f, "{{tempo:{bpm}}}"),
+            Directive::Time { beats, unit } => write!(f, "{{time:{beats}/{unit}}}"),
+            Directive::Capo(capo) => write!(f, "{{capo:{capo}}}"),
+            Directive::Meta { name, value } => write!(f, "{{meta:{name} {value}}}"),
+            Directive::Instrument(instrument) => write!(f, "{{instrument:{instrument}}}"),
+            Directive::Define(definition) => write!(f, "{{define:{definition}}}"),
+            Directive::Chord(definition) => write!(f, "{{chord:{definition}}}"),
+            Directive::Columns(columns) => write!(f, "{{columns:{columns}}}"),
+            Directive::ColumnBreak => write!(f, "{{column_break}}"),
+            Directive::PageBreak { physical: false } => write!(f, "{{new_page}}"),
+            Directive::PageBreak { physical: true } => write!(f, "{{new_physical_page}}"),
+            Directive::NewSong => write!(f, "{{new_song}}"),
+            Directive::ChorusRecall => write!(f, "{{chorus}}"),
+            Directive::SectionStart { kind, label: None } => write!(f, "{{start_of_{kind}}}"),
+            Directive::SectionStart {
+                kind,
+                label: Some(label),
+            } => write!(f, "{{start_of_{kind}:{label}}}"),
+            Directive::SectionEnd { kind } => write!(f, "{{end_of_{kind}}}"),
             Directive::Other(content) => write!(f, "{{{content}}}"),
         }
     }
 }
+
+impl fmt::Display for SectionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SectionKind::Chorus => write!(f, "chorus"),
+            SectionKind::Verse => write!(f, "verse"),
+            SectionKind::Bridge => write!(f, "bridge"),
+            SectionKind::Tab => write!(f, "tab"),
+            SectionKind::Grid => write!(f, "grid"),
+        }
+    }
+}
+
+impl fmt::Display for CommentStyle {
+    /// Writes the name of the directive for this style of comment.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommentStyle::Plain => write!(f, "comment"),
+            CommentStyle::Italic => write!(f, "comment_italic"),
+            CommentStyle::Box => write!(f, "comment_box"),
+        }
+    }
+}
+
+impl fmt::Display for ChordDefinition {
+    /// Writes the definition as it would appear after `{define:`. Compact
+    /// definitions are written compactly unless a number has two digits.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let compact = self.compact
+            && self.frets.iter().flatten().all(|&fret| fret <= 9)
+            && self.fingers.iter().flatten().all(|&finger| finger <= 9);
+        let separator = if compact { "" } else { " " };
+
+        write!(f, "{} frets ", self.name)?;
+        let frets = self
+            .frets
+            .iter()
+            .map(|fret| match fret {
+                Some(fret) => fret.to_string(),
+                None => "x".to_owned(),
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", frets.join(separator))?;
+        if let Some(fingers) = &self.fingers {
+            let fingers = fingers.iter().map(u8::to_string).collect::<Vec<_>>();
+            write!(f, " fingers {}", fingers.join(separator))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,325 @@
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    chordpro::{
+        charts::KeyError,
+        directives::{Directive, SectionKind},
+        parser::{self, ChartParseOptions},
+    },
+    theory::{
+        chords::Chord,
+        notes::{Note, Spelling},
+        scales::Scale,
+    },
+};
+
+/// A chart that keeps the text it was parsed from, so that it can be written
+/// back out with nothing changed but the chords and the key.
+///
+/// Unlike [`Chart`](crate::chordpro::charts::Chart), which normalizes spacing
+/// and directives, displaying a `LosslessChart` that has not been changed
+/// gives back exactly the original text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LosslessChart {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    /// A chord written inline, without the brackets around it.
+    Chord(Chord),
+    /// The value of a `{key}` directive.
+    Key(Scale),
+    /// A row of chords above lyrics, with the column of each chord.
+    ChordRow(Vec<(usize, Chord)>),
+}
+
+impl LosslessChart {
+    /// The key from the first `{key}` directive, or failing that the key
+    /// detected from the chords.
+    pub fn key(&self) -> Option<Scale> {
+        self.segments
+            .iter()
+            .find_map(|segment| match segment {
+                &Segment::Key(key) => Some(key),
+                _ => None,
+            })
+            .or_else(|| Scale::detect(self.chords()))
+    }
+
+    /// Every chord in the chart, in order.
+    pub fn chords(&self) -> impl Iterator<Item = &Chord> {
+        self.segments.iter().flat_map(|segment| match segment {
+            Segment::Chord(chord) => vec![chord],
+            Segment::ChordRow(chords) => chords.iter().map(|(_, chord)| chord).collect(),
+            _ => Vec::new(),
+        })
+    }
+
+    pub fn transpose_to(&mut self, new_key: Scale) -> Result<(), KeyError> {
+        let old_key = self.key().ok_or(KeyError::Missing)?;
+        let transpose = |note: Note| {
+            let note = note.as_scale_degree(old_key).in_key(new_key);
+            note.as_midi()
+                .spelled(Spelling::FollowKey)
+                .unwrap_or(note)
+                .into()
+        };
+        let transpose_chord = |chord: &mut Chord| {
+            chord.root = transpose(chord.root);
+            chord.bass = chord.bass.map(transpose);
+        };
+
+        for segment in &mut self.segments {
+            match segment {
+                Segment::Text(_) => {}
+                Segment::Chord(chord) => transpose_chord(chord),
+                Segment::Key(key) => *key = new_key,
+                Segment::ChordRow(chords) => {
+                    for (_, chord) in chords {
+                        transpose_chord(chord);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Transposes the chart by a number of semitones, moving each chord with
+    /// [`Chord::transpose`] and respelling it in the new key where it fits the
+    /// scale. The new key is spelled with flats where there is a choice.
+    pub fn transpose_by(&mut self, semitones: i8) -> Result<(), KeyError> {
+        let old_key = self.key().ok_or(KeyError::Missing)?;
+        let new_key = Scale((old_key.0.as_midi() + semitones).as_letter());
+        for segment in &mut self.segments {
+            match segment {
+                Segment::Text(_) => {}
+                Segment::Chord(chord) => {
+                    *chord = chord
+                        .transpose(semitones, Spelling::FollowKey)
+                        .spelled_in(new_key)
+                }
+                Segment::Key(key) => *key = new_key,
+                Segment::ChordRow(chords) => {
+                    for (_, chord) in chords {
+                        *chord = chord
+                            .transpose(semitones, Spelling::FollowKey)
+                            .spelled_in(new_key);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a chart with the given options, splitting the input into text
+    /// and chords by the same rules as
+    /// [`Chart::parse_with`](crate::chordpro::charts::Chart::parse_with).
+    /// Nothing is rejected, as anything that is not a chord is kept as text.
+    pub fn parse_with(input: &str, options: &ChartParseOptions) -> LosslessChart {
+        let mut chart = LosslessChart {
+            segments: Vec::new(),
+        };
+        // The kind of tab or grid section being read, whose lines are kept as
+        // is.
+        let mut verbatim = None;
+        for line in input.split_inclusive('\n') {
+            let content = line.trim_end_matches(['\r', '\n']);
+            let ending = &line[content.len()..];
+
+            match (parser::parse_directive(content, options), verbatim) {
+                (Some(Directive::SectionEnd { kind }), Some(open)) if kind == open => {
+                    verbatim = None;
+                    chart.push_text(content);
+                }
+                (_, Some(_)) => chart.push_text(content),
+                (Some(directive), None) => {
+                    if let Directive::SectionStart {
+                        kind: kind @ (SectionKind::Tab | SectionKind::Grid),
+                        ..
+                    } = directive
+                    {
+                        verbatim = Some(kind);
+                    }
+                    chart.push_directive_line(content, &directive);
+                }
+                (None, None) => match parser::parse_chord_row(content, options) {
+                    Ok(chords)
+                        if options.extensions
+                            && !chords.is_empty()
+                            && parser::is_chord_line(&chords, options)
+                            && !content.contains('[') =>
+                    {
+                        let row = content.trim_end();
+                        chart.segments.push(Segment::ChordRow(chords));
+                        chart.push_text(&content[row.len()..]);
+                    }
+                    _ => chart.push_inline_line(content, options),
+                },
+            }
+            chart.push_text(ending);
+        }
+        chart
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if let Some(Segment::Text(last)) = self.segments.last_mut() {
+            last.push_str(text);
+        } else if !text.is_empty() {
+            self.segments.push(Segment::Text(text.to_owned()));
+        }
+    }
+
+    fn push_directive_line(&mut self, line: &str, directive: &Directive) {
+        if let Directive::Key(key) = directive
+            && let Some((name, rest)) = line.split_once(':')
+            && let Some((value, end)) = rest.split_once('}')
+        {
+            let leading = value.len() - value.trim_start().len();
+            let trailing = value.len() - value.trim_end().len();
+            self.push_text(name);
+            self.push_text(":");
+            self.push_text(&value[..leading]);
+            self.segments.push(Segment::Key(*key));
+            self.push_text(&value[value.len() - trailing..]);
+            self.push_text("}");
+            self.push_text(end);
+        } else {
+            self.push_text(line);
+        }
+    }
+
+    fn push_inline_line(&mut self, mut line: &str, options: &ChartParseOptions) {
+        while let Some(open) = line.find('[') {
+            let Some(close) = line[open..].find(']').map(|close| open + close) else {
+                break;
+            };
+            match parser::parse_chord(&line[open + 1..close], options) {
+                Ok(chord) => {
+                    self.push_text(&line[..open]);
+                    self.segments.push(Segment::Chord(chord));
+                }
+                Err(_) => self.push_text(&line[..=close]),
+            }
+            line = &line[close + 1..];
+        }
+        self.push_text(line);
+    }
+}
+
+impl FromStr for LosslessChart {
+    type Err = Infallible;
+
+    /// Parses with the same options as [`str::parse`] for a
+    /// [`Chart`](crate::chordpro::charts::Chart).
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Ok(LosslessChart::parse_with(input, &parser::default_options()))
+    }
+}
+
+impl fmt::Display for LosslessChart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => write!(f, "{text}")?,
+                Segment::Chord(chord) => write!(f, "[{chord}]")?,
+                Segment::Key(key) => write!(f, "{key}")?,
+                Segment::ChordRow(chords) => {
+                    // Each chord stays in its column unless the chord before
+                    // it has grown into that column.
+                    let mut column = None;
+                    for (start, chord) in chords {
+                        let start = match column {
+                            Some(column) => (*start).max(column + 1),
+                            None => *start,
+                        };
+                        let chord = chord.to_string();
+                        write!(f, "{:1$}{chord}", "", start - column.unwrap_or(0))?;
+                        column = Some(start + chord.width());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chordpro::{lossless::LosslessChart, parser::ChartParseOptions},
+        theory::{chords::Chord, notes::Letter::*, scales::Scale},
+    };
+
+    const O_HOLY_NIGHT: &str = include_str!("../../examples/O-Holy-Night-.chordpro");
+
+    #[test]
+    fn test_round_trip() {
+        let chart = LosslessChart::parse_with(O_HOLY_NIGHT, &ChartParseOptions::with_extensions());
+
+        assert_eq!(chart.to_string(), O_HOLY_NIGHT);
+    }
+
+    #[test]
+    fn test_transpose_only_chords() {
+        let mut chart =
+            LosslessChart::parse_with(O_HOLY_NIGHT, &ChartParseOptions::with_extensions());
+        chart.transpose_to(Scale(B.flat())).unwrap();
+        let output = chart.to_string();
+
+        assert_eq!(output.lines().count(), O_HOLY_NIGHT.lines().count());
+        for (old, new) in O_HOLY_NIGHT.split('\n').zip(output.split('\n')) {
+            let is_chord_row = old
+                .split_whitespace()
+                .all(|word| word.parse::<Chord>().is_ok())
+                && !old.trim().is_empty();
+            if old == "{key:G}" {
+                assert_eq!(new, "{key:Bb}");
+            } else if !is_chord_row {
+                assert_eq!(new, old);
+            }
+        }
+        assert!(output.contains("Intro\r\nBb   F    Gm    Eb \r\n"));
+        assert!(output.contains("           Bb           F              Bb\r\n"));
+    }
+
+    #[test]
+    fn test_transpose_inline() {
+        let mut chart =
+            "{ key : G }\r\n[G]Lorem  [D/F#]ipsum [*Slowly]\n\n{start_of_tab}\n[G]\n{end_of_tab}\n"
+                .parse::<LosslessChart>()
+                .unwrap();
+        chart.transpose_by(2).unwrap();
+
+        assert_eq!(
+            chart.to_string(),
+            "{ key : A }\r\n[A]Lorem  [E/G#]ipsum [*Slowly]\n\n{start_of_tab}\n[G]\n{end_of_tab}\n"
+        );
+    }
+
+    #[test]
+    fn test_transpose_chord_row_crowding() {
+        let mut chart =
+            LosslessChart::parse_with("{key:C}\nC D\nLorem", &ChartParseOptions::with_extensions());
+        chart.transpose_to(Scale(C.sharp())).unwrap();
+
+        assert_eq!(chart.to_string(), "{key:C#}\nC# D#\nLorem");
+    }
+
+    #[test]
+    fn test_transpose_chord_row_unknown_qualities() {
+        let mut chart = LosslessChart::parse_with(
+            "{key:G}\nG      C2     Dsus\nLorem  ipsum  dolor",
+            &ChartParseOptions::with_extensions(),
+        );
+        chart.transpose_by(2).unwrap();
+
+        assert_eq!(
+            chart.to_string(),
+            "{key:A}\nA      D2     Esus\nLorem  ipsum  dolor"
+        );
+    }
+}
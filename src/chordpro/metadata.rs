@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    chordpro::{
+        charts::{Chart, Line},
+        directives::Directive,
+    },
+    theory::scales::Scale,
+};
+
+/// The song-level information from a chart's directives. Where a directive
+/// appears more than once, the first occurrence is used.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChartMetadata {
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub artist: Option<String>,
+    pub composer: Option<String>,
+    pub album: Option<String>,
+    pub key: Option<Scale>,
+    pub tempo: Option<u32>,
+    /// The time signature, as beats per bar and beat unit.
+    pub time: Option<(u8, u8)>,
+    pub capo: Option<u8>,
+    /// Values from `{meta}` directives, by name.
+    pub meta: BTreeMap<String, String>,
+}
+
+impl Chart {
+    pub fn metadata(&self) -> ChartMetadata {
+        let mut metadata = ChartMetadata {
+            key: self.key(),
+            tempo: self.tempo(),
+            time: self.time_signature(),
+            ..ChartMetadata::default()
+        };
+        for line in &self.lines {
+            let Line::Directive(directive) = line else {
+                continue;
+            };
+            match directive {
+                Directive::Title(title) => {
+                    metadata.title.get_or_insert_with(|| title.clone());
+                }
+                Directive::Subtitle(subtitle) => {
+                    metadata.subtitle.get_or_insert_with(|| subtitle.clone());
+                }
+                Directive::Artist(artist) => {
+                    metadata.artist.get_or_insert_with(|| artist.clone());
+                }
+                Directive::Composer(composer) => {
+                    metadata.composer.get_or_insert_with(|| composer.clone());
+                }
+                Directive::Album(album) => {
+                    metadata.album.get_or_insert_with(|| album.clone());
+                }
+                Directive::Capo(capo) => {
+                    metadata.capo.get_or_insert(*capo);
+                }
+                Directive::Meta { name, value } => {
+                    metadata
+                        .meta
+                        .entry(name.clone())
+                        .or_insert_with(|| value.clone());
+                }
+                _ => {}
+            }
+        }
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        chordpro::{charts::Chart, metadata::ChartMetadata},
+        theory::{notes::Letter, scales::Scale},
+    };
+
+    const HOW_GREAT_THOU_ART: &str =
+        include_str!("../../examples/How-Great-Thou-Art-(Whakaaria-Mai).chordpro");
+    const O_HOLY_NIGHT: &str = include_str!("../../examples/O-Holy-Night-.chordpro");
+
+    #[test]
+    fn test_metadata() {
+        let chart = HOW_GREAT_THOU_ART.parse::<Chart>().unwrap();
+        assert_eq!(
+            chart.metadata(),
+            ChartMetadata {
+                title: Some("How Great Thou Art (Whakaaria Mai)".to_owned()),
+                key: Some(Scale(Letter::B.flat())),
+                tempo: Some(76),
+                ..ChartMetadata::default()
+            }
+        );
+
+        let chart = format!("{{capo:2}}\n{{meta: year 2020}}\n{O_HOLY_NIGHT}")
+            .parse::<Chart>()
+            .unwrap();
+        assert_eq!(
+            chart.metadata(),
+            ChartMetadata {
+                title: Some("O Holy Night".to_owned()),
+                subtitle: Some("Hillsong Worship".to_owned()),
+                artist: None,
+                composer: None,
+                album: Some("The Peace Project".to_owned()),
+                key: Some(Scale(Letter::G.natural())),
+                tempo: Some(84),
+                time: Some((6, 8)),
+                capo: Some(2),
+                meta: BTreeMap::from([("year".to_owned(), "2020".to_owned())]),
+            }
+        );
+    }
+}
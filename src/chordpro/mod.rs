@@ -1,3 +1,11 @@
+pub mod builder;
+pub mod capo;
 pub mod charts;
+pub mod diff;
 pub mod directives;
+pub mod lossless;
+pub mod metadata;
 pub mod parser;
+pub mod sections;
+pub mod transforms;
+pub mod validation;
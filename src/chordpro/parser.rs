@@ -1,44 +1,187 @@
-use std::{cell::Cell, str::FromStr};
+use std::{borrow::Cow, cell::Cell, fmt, str::FromStr};
 
 use nom::{
-    IResult, Parser,
+    Parser,
     branch::alt,
-    bytes::complete::{tag, take_until, take_while, take_while1},
-    character::complete::{line_ending, one_of, space0, space1},
-    combinator::{eof, opt, success},
-    multi::{many_till, many0, separated_list1},
+    bytes::complete::{tag, tag_no_case, take_till1, take_until, take_while, take_while1},
+    character::complete::{char, digit1, line_ending, one_of, satisfy, space0, space1},
+    combinator::{cut, eof, map_res, not, opt, peek, recognize, success, verify},
+    error::{ContextError, ErrorKind, FromExternalError, context},
+    multi::{fold_many1, many_till, many0, many1, separated_list1},
+    sequence::preceded,
 };
 
+use unicode_width::UnicodeWidthStr;
+
 use crate::{
     chordpro::{
-        charts::{Chart, Chunk, Line},
-        directives::Directive,
+        charts::{Chart, Chunk, Line, LineCol, LineEnding, Source},
+        directives::{ChordDefinition, CommentStyle, Directive, SectionKind},
     },
     theory::{
         chords::{Chord, ChordQuality},
-        notes::{Accidental, Letter, LetterNote, Note},
+        instruments::Instrument,
+        notes::{Accidental, Letter, LetterNote, Note, NoteNaming},
+        qualities::{Alteration, Extension, StructuredQuality, Sus, Triad},
         scales::{Scale, ScaleDegree},
     },
 };
 
-type Span<'input> = nom_locate::LocatedSpan<&'input str>;
-type Error<'input> = nom::error::Error<Span<'input>>;
+/// The text being parsed, which carries the options it is parsed with.
+type Span<'input> = nom_locate::LocatedSpan<&'input str, ChartParseOptions>;
+type IResult<I, O> = nom::IResult<I, O, Error<I>>;
+
+/// The error from the parsers. It keeps the message of the innermost
+/// [`context`] the parser failed in, and points at where that context began.
+#[derive(Debug, Clone, PartialEq)]
+struct Error<I> {
+    input: I,
+    kind: ErrorKind,
+    context: Option<&'static str>,
+}
+
+impl<I> nom::error::ParseError<I> for Error<I> {
+    fn from_error_kind(input: I, kind: ErrorKind) -> Self {
+        Error {
+            input,
+            kind,
+            context: None,
+        }
+    }
+
+    fn append(_input: I, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<I> ContextError<I> for Error<I> {
+    fn add_context(input: I, context: &'static str, mut other: Self) -> Self {
+        if other.context.is_none() {
+            other.input = input;
+            other.context = Some(context);
+        }
+        other
+    }
+}
+
+impl<I, E> FromExternalError<I, E> for Error<I> {
+    fn from_external_error(input: I, kind: ErrorKind, _error: E) -> Self {
+        nom::error::ParseError::from_error_kind(input, kind)
+    }
+}
 
 thread_local! {
-    static EXTENSIONS_ENABLED: Cell<bool> = Cell::new(false);
+    static EXTENSIONS_ENABLED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Options for parsing a chart with [`Chart::parse_with`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChartParseOptions {
+    /// Whether to accept syntax beyond ChordPro, such as rows of chords above
+    /// the lyrics and lines continued with a backslash.
+    pub extensions: bool,
+    /// Whether to read a row of chords directly above a line of lyrics as
+    /// "chords above" even without `extensions`, as in charts pasted from
+    /// guitar tab sites. Only rows where every chord is well formed are
+    /// accepted, so that lyrics such as "Be A Bee" are left alone.
+    pub auto_detect_chord_lines: bool,
+    /// With `extensions`, whether to skip a single blank line between a row of
+    /// chords and its lyrics, as some exported charts have, so that the chords
+    /// are paired with the lyrics two lines down. This is off by default, as a
+    /// row of chords before a blank line is more often an intro or a
+    /// turnaround with no lyrics.
+    pub skip_blank_after_chords: bool,
+    /// Whether to accept lowercase letters such as `c` and `am` as the notes
+    /// of chords, as some tab sites write them. A lowercase `b` followed by a
+    /// digit is still read as a flattened scale degree, as in `b7`.
+    pub lowercase_chords: bool,
+    /// With `lowercase_chords`, whether a lowercase root makes a major chord
+    /// minor, so that `a` is read as Am and `d7` as Dm7.
+    pub lowercase_minor: bool,
+    /// The names that notes are written with. With [`NoteNaming::German`], `H`
+    /// is read as B natural and `B` as B flat.
+    pub note_naming: NoteNaming,
+}
+
+impl ChartParseOptions {
+    /// The default options with `extensions` turned on.
+    pub const fn with_extensions() -> Self {
+        ChartParseOptions {
+            extensions: true,
+            auto_detect_chord_lines: false,
+            skip_blank_after_chords: false,
+            lowercase_chords: false,
+            lowercase_minor: false,
+            note_naming: NoteNaming::English,
+        }
+    }
 }
 
-/// Enables or disables extensions **for the current thread**.
+/// Enables or disables extensions **for the current thread**. This only
+/// affects [`str::parse`], as [`Chart::parse_with`] always uses the options
+/// it is given.
+#[deprecated(note = "use `Chart::parse_with` and `ChartParseOptions` instead")]
 pub fn set_extensions_enabled(enabled: bool) {
     EXTENSIONS_ENABLED.with(|cell| cell.set(enabled));
 }
 
+/// The options used by [`str::parse`]: the defaults, apart from extensions if
+/// they were enabled with [`set_extensions_enabled`].
+pub(crate) fn default_options() -> ChartParseOptions {
+    ChartParseOptions {
+        extensions: EXTENSIONS_ENABLED.with(|cell| cell.get()),
+        ..Default::default()
+    }
+}
+
 fn chart(input: Span) -> IResult<Span, Chart> {
+    // The kind of tab or grid section being read, whose lines are kept as is.
+    let mut verbatim = None;
+    let line = move |input| {
+        let position = line_col(&input);
+        let (rest, line) = match verbatim {
+            Some(kind) => match line(input) {
+                Ok((rest, line @ Line::Directive(Directive::SectionEnd { kind: end })))
+                    if end == kind =>
+                {
+                    verbatim = None;
+                    (rest, line)
+                }
+                _ => verbatim_line(input)?,
+            },
+            None => line(input)?,
+        };
+        if let Line::Directive(Directive::SectionStart {
+            kind: kind @ (SectionKind::Tab | SectionKind::Grid),
+            ..
+        }) = line
+        {
+            verbatim = Some(kind);
+        }
+        Ok((rest, (line, Some(position))))
+    };
+
     many_till((line, opt(line_ending)).map(|(line, _)| line), eof)
-        .map(|(lines, _)| Chart { lines })
+        .map(|(lines, _)| {
+            let (lines, positions) = lines.into_iter().unzip();
+            Chart {
+                lines,
+                source: Source {
+                    positions,
+                    line_ending: LineEnding::Lf,
+                },
+            }
+        })
         .parse(input)
 }
 
+fn line_col(input: &Span) -> LineCol {
+    LineCol {
+        line: input.location_line(),
+        column: input.get_utf8_column(),
+    }
+}
+
 fn line(input: Span) -> IResult<Span, Line> {
     alt((
         directive.map(Line::Directive),
@@ -54,20 +197,113 @@ fn line(input: Span) -> IResult<Span, Line> {
     .parse(input)
 }
 
+fn verbatim_line(input: Span) -> IResult<Span, Line> {
+    // The `\r` of a `\r\n` line ending is kept, so that the line is written
+    // back out with the ending it had.
+    take_while(|c| c != '\n')
+        .map(|text: Span| Line::Verbatim((*text).to_owned()))
+        .parse(input)
+}
+
 fn directive(input: Span) -> IResult<Span, Directive> {
-    (tag::<_, _, Error>("{"), take_until("}"), tag("}"))
+    let options = input.extra;
+    (tag::<_, _, Error<Span>>("{"), take_until("}"), tag("}"))
         .map(|(_, content, _)| {
-            match content.split_once(':') {
+            if let Some(directive) = section_directive(&content) {
+                return directive;
+            }
+            match content.trim().to_ascii_lowercase().as_str() {
+                "column_break" | "colb" => return Directive::ColumnBreak,
+                "new_page" | "np" => return Directive::PageBreak { physical: false },
+                "new_physical_page" | "npp" => return Directive::PageBreak { physical: true },
+                "new_song" | "ns" => return Directive::NewSong,
+                "chorus" => return Directive::ChorusRecall,
+                _ => {}
+            }
+
+            // Directive names are case-insensitive. Spaces around both the
+            // name and the value are ignored, but those inside the value are
+            // kept as written.
+            let name_value = content
+                .split_once(':')
+                .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim()));
+            match name_value
+                .as_ref()
+                .map(|(name, value)| (name.as_str(), *value))
+            {
                 Some(("title", title)) => return Directive::Title(title.to_owned()),
-                Some(("comment", comment)) => return Directive::Comment(comment.to_owned()),
+                Some(("sorttitle", title)) => return Directive::SortTitle(title.to_owned()),
+                Some(("subtitle", subtitle)) => return Directive::Subtitle(subtitle.to_owned()),
+                Some(("artist", artist)) => return Directive::Artist(artist.to_owned()),
+                Some(("composer", composer)) => return Directive::Composer(composer.to_owned()),
+                Some(("album", album)) => return Directive::Album(album.to_owned()),
+                Some((
+                    name @ ("comment" | "c" | "comment_italic" | "ci" | "comment_box" | "cb"),
+                    text,
+                )) => {
+                    let style = match name {
+                        "comment_italic" | "ci" => CommentStyle::Italic,
+                        "comment_box" | "cb" => CommentStyle::Box,
+                        _ => CommentStyle::Plain,
+                    };
+                    return Directive::Comment {
+                        text: text.to_owned(),
+                        style,
+                    };
+                }
                 Some(("key", key)) => {
-                    if let Ok(key) = key.parse() {
+                    if key.trim().eq_ignore_ascii_case("none") {
+                        return Directive::NoKey;
+                    }
+                    if let Ok(key) = Scale::parse_with(key.trim(), &options) {
                         return Directive::Key(key);
                     }
                 }
                 Some(("tempo", tempo)) => {
-                    if let Ok(tempo) = tempo.trim().parse() {
-                        return Directive::Tempo(tempo);
+                    if let Some(tempo) = tempo_directive(tempo.trim()) {
+                        return tempo;
+                    }
+                }
+                Some(("time", time)) => {
+                    if let Some((beats, unit)) = time.trim().split_once('/')
+                        && let (Ok(beats @ 1..=32), Ok(unit)) =
+                            (beats.trim().parse(), unit.trim().parse::<u8>())
+                        && unit.is_power_of_two()
+                    {
+                        return Directive::Time { beats, unit };
+                    }
+                }
+                Some(("columns" | "col", columns)) => {
+                    if let Ok(columns @ 1..) = columns.trim().parse() {
+                        return Directive::Columns(columns);
+                    }
+                }
+                Some(("capo", capo)) => {
+                    if let Ok(capo) = capo.trim().parse() {
+                        return Directive::Capo(capo);
+                    }
+                }
+                Some(("meta", meta)) => {
+                    if let Some((name, value)) = meta.trim().split_once(char::is_whitespace) {
+                        return Directive::Meta {
+                            name: name.to_owned(),
+                            value: value.trim().to_owned(),
+                        };
+                    }
+                }
+                Some(("instrument", instrument)) => {
+                    if let Ok(instrument) = instrument.trim().parse() {
+                        return Directive::Instrument(instrument);
+                    }
+                }
+                Some((name @ ("chord" | "define"), definition)) => {
+                    if let Ok((_, definition)) =
+                        chord_definition(Span::new_extra(definition, content.extra))
+                    {
+                        return match name {
+                            "chord" => Directive::Chord(definition),
+                            _ => Directive::Define(definition),
+                        };
                     }
                 }
                 _ => {}
@@ -77,60 +313,291 @@ fn directive(input: Span) -> IResult<Span, Directive> {
         .parse(input)
 }
 
-fn chords_over_lyrics_content<'a>(input: Span<'a>) -> IResult<Span<'a>, Vec<Chunk>> {
-    let extensions_enabled = EXTENSIONS_ENABLED.with(|cell| cell.get());
-    if !extensions_enabled {
-        return Err(nom::Err::Error(Error::new(
-            input,
-            nom::error::ErrorKind::Tag,
-        )));
-    }
+/// Parses a directive at the start of a line.
+pub(crate) fn parse_directive(input: &str, options: &ChartParseOptions) -> Option<Directive> {
+    directive
+        .parse(Span::new_extra(input, *options))
+        .ok()
+        .map(|(_, directive)| directive)
+}
 
-    let start_len = input.len();
-    (
+fn section_directive(content: &str) -> Option<Directive> {
+    let (name, label) = match content.split_once(':') {
+        Some((name, label)) => (name, Some(label.trim()).filter(|l| !l.is_empty())),
+        None => (content, None),
+    };
+    let (start, kind) = match name.trim().to_ascii_lowercase().as_str() {
+        "start_of_chorus" | "soc" => (true, SectionKind::Chorus),
+        "end_of_chorus" | "eoc" => (false, SectionKind::Chorus),
+        "start_of_verse" | "sov" => (true, SectionKind::Verse),
+        "end_of_verse" | "eov" => (false, SectionKind::Verse),
+        "start_of_bridge" | "sob" => (true, SectionKind::Bridge),
+        "end_of_bridge" | "eob" => (false, SectionKind::Bridge),
+        "start_of_tab" | "sot" => (true, SectionKind::Tab),
+        "end_of_tab" | "eot" => (false, SectionKind::Tab),
+        "start_of_grid" | "sog" => (true, SectionKind::Grid),
+        "end_of_grid" | "eog" => (false, SectionKind::Grid),
+        _ => return None,
+    };
+    Some(if start {
+        Directive::SectionStart {
+            kind,
+            label: label.map(str::to_owned),
+        }
+    } else {
+        Directive::SectionEnd { kind }
+    })
+}
+
+fn chord_definition(input: Span) -> IResult<Span, ChordDefinition> {
+    let definition = (
         space0,
-        separated_list1(space1, |input: Span<'a>| {
-            let index = start_len - input.len();
-            alt((boxed_chord, chord))
-                .map(|chord| (index, chord))
-                .parse(input)
-        }),
+        take_till1(|c: char| c.is_whitespace()),
+        space1,
+        tag("frets"),
+        space1,
+        alt((
+            (compact_frets, peek(end_of_frets)).map(|(frets, _)| (frets, true)),
+            separated_list1(space1, fret).map(|frets| (frets, false)),
+        )),
+        opt((space1, tag("fingers"), space1, fingers).map(|(_, _, _, fingers)| fingers)),
         space0,
+        eof,
+    )
+        .map(
+            |(_, name, _, _, _, (frets, compact_frets), fingers, _, _)| ChordDefinition {
+                name: (*name).to_owned(),
+                frets,
+                compact: compact_frets && fingers.as_ref().is_none_or(|(_, compact)| *compact),
+                fingers: fingers.map(|(fingers, _)| fingers),
+            },
+        );
+    verify(definition, |definition: &ChordDefinition| {
+        definition
+            .fingers
+            .as_ref()
+            .is_none_or(|fingers| fingers.len() == definition.frets.len())
+    })
+    .parse(input)
+}
+
+fn end_of_frets(input: Span) -> IResult<Span, ()> {
+    (space0, alt((eof, tag("fingers"))))
+        .map(|_| ())
+        .parse(input)
+}
+
+/// Parses fingers written either as a single string, e.g. `032010`, or
+/// separated by spaces, along with whether they were written as one string.
+fn fingers(input: Span) -> IResult<Span, (Vec<u8>, bool)> {
+    alt((
+        (
+            many1(one_of("0123456789").map(|c| c as u8 - b'0')),
+            peek((space0, eof)),
+        )
+            .map(|(fingers, _)| (fingers, true)),
+        separated_list1(space1, map_res(digit1, |d: Span| d.parse::<u8>()))
+            .map(|fingers| (fingers, false)),
+    ))
+    .parse(input)
+}
+
+/// Parses frets written as a single string, e.g. `x02210`.
+fn compact_frets(input: Span) -> IResult<Span, Vec<Option<u8>>> {
+    many1(one_of("0123456789xX").map(|c| c.to_digit(10).map(|d| d as u8))).parse(input)
+}
+
+fn fret(input: Span) -> IResult<Span, Option<u8>> {
+    alt((
+        tag_no_case("x").map(|_| None),
+        map_res(digit1, |d: Span| d.parse::<u8>()).map(Some),
+    ))
+    .parse(input)
+}
+
+fn instrument(input: Span) -> IResult<Span, Instrument> {
+    alt((
+        tag_no_case("guitar").map(|_| Instrument::Guitar),
+        tag_no_case("ukulele").map(|_| Instrument::Ukulele),
+        tag_no_case("mandolin").map(|_| Instrument::Mandolin),
+        tag_no_case("bass").map(|_| Instrument::Bass),
+    ))
+    .parse(input)
+}
+
+/// Parses a row of chords and the lyrics below it. With
+/// [`ChartParseOptions::skip_blank_after_chords`], a single blank line between
+/// the chords and the lyrics is skipped, and is not kept in the chart, as long
+/// as the line after it is not another row of chords or a directive.
+fn chords_over_lyrics_content(input: Span) -> IResult<Span, Vec<Chunk>> {
+    let options = input.extra;
+    if !options.extensions {
+        if !options.auto_detect_chord_lines {
+            return Err(nom::Err::Error(nom::error::ParseError::from_error_kind(
+                input,
+                ErrorKind::Tag,
+            )));
+        }
+        return detected_chords_over_lyrics_content(input);
+    }
+
+    (
+        chord_line,
         alt((
             eof.map(|_| ""),
             (line_ending, eof).map(|(_, _)| ""),
+            (
+                verify(success(()), |_| options.skip_blank_after_chords),
+                line_ending,
+                space0,
+                line_ending,
+                lyrics_after_blank_line,
+            )
+                .map(|(_, _, _, _, s)| s),
             (
                 line_ending,
-                take_while::<_, Span, Error>(|c| c != '\r' && c != '\n'),
+                take_while::<_, Span, Error<Span>>(|c| c != '\r' && c != '\n'),
             )
-                .map::<_, &str>(|(_, s)| &*s),
+                .map::<_, &str>(|(_, s)| *s),
         )),
     )
-        .map(|(_, chords, _, lyrics)| {
-            let mut chunks = Vec::new();
-            if chords[0].0 != 0 {
-                let index = chords[0].0.min(lyrics.len());
-                chunks.push(Chunk {
-                    chord: None,
-                    lyrics: lyrics[..index].to_owned(),
-                });
-            }
-            for (i, (start_index, chord)) in chords.iter().enumerate() {
-                let start_index = (*start_index).min(lyrics.len());
-                let end_index = chords
-                    .get(i + 1)
-                    .map_or(usize::MAX, |&(next_index, _)| next_index)
-                    .min(lyrics.len());
-                chunks.push(Chunk {
-                    chord: Some(chord.clone()),
-                    lyrics: lyrics[start_index..end_index].to_owned(),
-                });
-            }
-            chunks
-        })
+        .map(|(chords, lyrics)| attach_chords_to_source(&chords, lyrics))
+        .parse(input)
+}
+
+/// Parses a row of chords directly above a line of lyrics, for
+/// [`ChartParseOptions::auto_detect_chord_lines`].
+fn detected_chords_over_lyrics_content(input: Span) -> IResult<Span, Vec<Chunk>> {
+    (chord_line, line_ending, lyrics_after_blank_line)
+        .map(|(chords, _, lyrics)| attach_chords_to_source(&chords, lyrics))
+        .parse(input)
+}
+
+/// Parses a non-empty line of plain lyrics, which is not a row of chords, a
+/// directive or a line with inline chords.
+fn lyrics_after_blank_line<'a>(input: Span<'a>) -> IResult<Span<'a>, &'a str> {
+    (
+        not((chord_line, alt((line_ending, eof)))),
+        not(tag("{")),
+        take_while1(is_lyrics_char),
+        peek(alt((line_ending, eof))),
+    )
+        .map(|(_, _, s, _): (_, _, Span<'a>, _)| *s.fragment())
+        .parse(input)
+}
+
+/// Parses a row of chords, returning each chord along with its column.
+fn chord_row<'a>(input: Span<'a>) -> IResult<Span<'a>, Vec<(usize, Chord)>> {
+    let start = *input.fragment();
+    (
+        space0,
+        separated_list1(space1, |input: Span<'a>| {
+            let index = start[..start.len() - input.len()].width();
+            alt((boxed_chord, chord))
+                .map(|chord| (index, chord))
+                .parse(input)
+        }),
+        space0,
+    )
+        .map(|(_, chords, _)| chords)
         .parse(input)
 }
 
+/// Parses a row of chords that passes [`is_chord_line`].
+fn chord_line(input: Span) -> IResult<Span, Vec<(usize, Chord)>> {
+    let options = input.extra;
+    verify(chord_row, |chords: &Vec<_>| is_chord_line(chords, &options)).parse(input)
+}
+
+/// Whether a row that parsed as chords should be read as chords rather than as
+/// lyrics. With extensions enabled, any row of chords is accepted, so that
+/// qualities such as `2` are kept. When the row is only auto-detected, words
+/// such as "Add" and "Be" parse as a root and a quality, so every quality must
+/// be one that is understood and a lone chord such as "A" is not accepted.
+pub(crate) fn is_chord_line(chords: &[(usize, Chord)], options: &ChartParseOptions) -> bool {
+    options.extensions
+        || (chords.len() >= 2
+            && chords
+                .iter()
+                .all(|(_, chord)| chord.quality.parse().is_ok()))
+}
+
+/// Splits `lyrics` as written below a row of chords into chunks, as in
+/// [`attach_chords`]. Columns count the escapes as written, and the escapes
+/// are then decoded as for inline lyrics.
+fn attach_chords_to_source(chords: &[(usize, Chord)], lyrics: &str) -> Vec<Chunk> {
+    let mut chunks = attach_chords(chords, lyrics);
+    for chunk in &mut chunks {
+        chunk.lyrics = unescape_lyrics(&chunk.lyrics);
+    }
+    chunks
+}
+
+/// Decodes `\[`, `\]` and `\\` in lyrics to the character after the
+/// backslash. Any other backslash is kept.
+fn unescape_lyrics(lyrics: &str) -> String {
+    let mut unescaped = String::with_capacity(lyrics.len());
+    let mut chars = lyrics.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(escaped @ ('[' | ']' | '\\'))) => {
+                unescaped.push(escaped);
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Splits `lyrics` into chunks at the column of each chord. Chords past the
+/// end of the lyrics become trailing chunks with no lyrics.
+pub(crate) fn attach_chords(chords: &[(usize, Chord)], lyrics: &str) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    if chords[0].0 != 0 {
+        let index = column_to_index(lyrics, chords[0].0);
+        chunks.push(Chunk {
+            chord: None,
+            lyrics: lyrics[..index].to_owned(),
+        });
+    }
+    for (i, (start_column, chord)) in chords.iter().enumerate() {
+        let start_index = column_to_index(lyrics, *start_column);
+        let end_index = chords.get(i + 1).map_or(lyrics.len(), |&(next_column, _)| {
+            column_to_index(lyrics, next_column)
+        });
+        chunks.push(Chunk {
+            chord: Some(chord.clone()),
+            lyrics: lyrics[start_index..end_index].to_owned(),
+        });
+    }
+    chunks
+}
+
+/// Finds the byte index of the first character of `text` at or after
+/// `column`, counting display width.
+fn column_to_index(text: &str, column: usize) -> usize {
+    text.char_indices()
+        .find(|&(i, _)| text[..i].width() >= column)
+        .map_or(text.len(), |(i, _)| i)
+}
+
+/// Parses a standalone row of chords, such as the chord line of a "chords
+/// above" pair. Returns an empty list if the row contains no chords.
+pub(crate) fn parse_chord_row(
+    input: &str,
+    options: &ChartParseOptions,
+) -> Result<Vec<(usize, Chord)>, String> {
+    if input.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    (chord_row, eof)
+        .map(|(chords, _)| chords)
+        .parse(Span::new_extra(input, *options))
+        .map(|(_, chords)| chords)
+        .map_err(|e| e.to_string())
+}
+
 fn inline_content(input: Span) -> IResult<Span, Vec<Chunk>> {
     many0(chunk).parse(input)
 }
@@ -141,18 +608,42 @@ fn is_lyrics_char(c: char) -> bool {
 
 fn chunk(input: Span) -> IResult<Span, Chunk> {
     alt((
-        (boxed_chord, take_while(is_lyrics_char)).map(|(chord, lyrics)| Chunk {
-            chord: Some(chord),
-            lyrics: (*lyrics).to_owned(),
-        }),
-        (take_while1(is_lyrics_char)).map(|lyrics: Span| Chunk {
+        (
+            preceded(
+                peek(tag("[")),
+                context("expected a chord", cut(boxed_chord)),
+            ),
+            opt(lyrics),
+        )
+            .map(|(chord, lyrics)| Chunk {
+                chord: Some(chord),
+                lyrics: lyrics.unwrap_or_default(),
+            }),
+        lyrics.map(|lyrics| Chunk {
             chord: None,
-            lyrics: (*lyrics).to_owned(),
+            lyrics,
         }),
     ))
     .parse(input)
 }
 
+/// Parses inline lyrics up to the next chord, where `\[`, `\]` and `\\` stand
+/// for literal brackets and backslashes.
+fn lyrics(input: Span) -> IResult<Span, String> {
+    fold_many1(
+        alt((
+            (char('\\'), one_of("[]\\")).map(|(_, c)| c),
+            satisfy(is_lyrics_char),
+        )),
+        String::new,
+        |mut lyrics, c| {
+            lyrics.push(c);
+            lyrics
+        },
+    )
+    .parse(input)
+}
+
 fn boxed_chord(input: Span) -> IResult<Span, Chord> {
     (tag("["), chord, tag("]"))
         .map(|(_, chord, _)| chord)
@@ -160,18 +651,199 @@ fn boxed_chord(input: Span) -> IResult<Span, Chord> {
 }
 
 fn chord(input: Span) -> IResult<Span, Chord> {
-    (note, chord_quality, opt((tag("/"), note).map(|(_, b)| b)))
-        .map(|(root, quality, bass)| Chord {
-            root,
-            quality,
-            bass,
+    let options = input.extra;
+    (
+        opt(peek(one_of("cdefgab"))),
+        note,
+        chord_quality,
+        opt((tag("/"), note).map(|(_, b)| b)),
+        opt(chord_annotation),
+    )
+        .map(|(lowercase, root, mut quality, bass, annotation)| {
+            let is_plain_major = quality
+                .parse()
+                .is_ok_and(|q| q.triad == Triad::Major && q.sus.is_none());
+            if lowercase.is_some()
+                && matches!(root, Note::Letter(_))
+                && options.lowercase_minor
+                && is_plain_major
+            {
+                quality = ChordQuality(format!("m{quality}"));
+            }
+            Chord {
+                root,
+                quality,
+                bass,
+                annotation,
+            }
         })
         .parse(input)
 }
 
+/// Takes the rhythm markings that Nashville charts put after a chord: `◊` for
+/// a diamond, `<` or `^` for a push, `>` for an accent and `.` for a staccato.
+fn chord_annotation(input: Span) -> IResult<Span, String> {
+    take_while1(|c| "◊<>^.".contains(c))
+        .map(|s: Span| (*s).to_owned())
+        .parse(input)
+}
+
+/// Takes the quality of a chord as written. Accidentals are only part of the
+/// quality when they alter a numbered note, as in `7b9`, so they cannot be
+/// confused with the accidental of a root or bass note. Likewise `o` is only
+/// diminished before a number, as in `Co7`, so that words such as "Go" are
+/// not chords, and parentheses must come in pairs, as in `C7(b9,#11)`.
 fn chord_quality(input: Span) -> IResult<Span, ChordQuality> {
-    take_while(|c: char| c.is_digit(10) || "Majminsusadd+-".contains(c))
-        .map(|s: Span| ChordQuality((*s).to_owned()))
+    let part = || {
+        alt((
+            tag("alt"),
+            recognize((one_of("b#♭♯"), digit1)),
+            recognize((tag("o"), digit1)),
+            recognize(one_of("0123456789Majminsusaddaug+-°Δø")),
+        ))
+    };
+    recognize(many0(alt((
+        recognize((tag("("), many1(alt((part(), tag(",")))), tag(")"))),
+        part(),
+    ))))
+    .map(|s: Span| ChordQuality((*s).to_owned()))
+    .parse(input)
+}
+
+fn structured_quality(input: Span) -> IResult<Span, StructuredQuality> {
+    alt((
+        (tag("5"), eof).map(|_| StructuredQuality {
+            triad: Triad::Power,
+            extensions: Vec::new(),
+            sus: None,
+            adds: Vec::new(),
+            alterations: Vec::new(),
+            altered: false,
+        }),
+        // Half-diminished, which is the same as `m7b5`.
+        (tag("ø"), opt(tag("7")), eof).map(|_| StructuredQuality {
+            triad: Triad::Minor,
+            extensions: vec![Extension::Seventh],
+            sus: None,
+            adds: Vec::new(),
+            alterations: vec![Alteration {
+                degree: 5,
+                accidental: Accidental::FLAT,
+            }],
+            altered: false,
+        }),
+        (
+            triad,
+            extensions,
+            opt(sus),
+            many0(add),
+            alterations,
+            opt(tag("alt")),
+            eof,
+        )
+            .map(|(triad, extensions, sus, adds, alterations, altered, _)| {
+                StructuredQuality {
+                    triad,
+                    extensions,
+                    sus,
+                    adds,
+                    alterations,
+                    altered: altered.is_some(),
+                }
+            }),
+    ))
+    .parse(input)
+}
+
+fn triad(input: Span) -> IResult<Span, Triad> {
+    alt((
+        alt((tag("dim"), tag("°"), tag("o"))).map(|_| Triad::Diminished),
+        alt((tag("aug"), tag("+"))).map(|_| Triad::Augmented),
+        alt((tag("min"), tag("-"))).map(|_| Triad::Minor),
+        (tag("m"), not(tag("aj"))).map(|_| Triad::Minor),
+        success(Triad::Major),
+    ))
+    .parse(input)
+}
+
+fn extensions(input: Span) -> IResult<Span, Vec<Extension>> {
+    alt((
+        (
+            alt((tag("maj"), tag("Maj"), tag("M"), tag("Δ"))),
+            opt(extension_number),
+        )
+            .map(|(_, n)| match n {
+                Some("6") => vec![Extension::Sixth],
+                Some(n) => stacked_extensions(Extension::MajorSeventh, n),
+                None => Vec::new(),
+            }),
+        extension_number.map(|n| match n {
+            "6" => vec![Extension::Sixth],
+            n => stacked_extensions(Extension::Seventh, n),
+        }),
+        success(Vec::new()),
+    ))
+    .parse(input)
+}
+
+fn extension_number<'a>(input: Span<'a>) -> IResult<Span<'a>, &'a str> {
+    alt((tag("13"), tag("11"), tag("9"), tag("7"), tag("6")))
+        .map(|n: Span| *n.fragment())
+        .parse(input)
+}
+
+/// The extensions implied by a chord number such as `9`, built on `seventh`.
+fn stacked_extensions(seventh: Extension, number: &str) -> Vec<Extension> {
+    match number {
+        "9" => vec![seventh, Extension::Ninth],
+        "11" => vec![seventh, Extension::Ninth, Extension::Eleventh],
+        "13" => vec![seventh, Extension::Ninth, Extension::Thirteenth],
+        _ => vec![seventh],
+    }
+}
+
+/// Parses altered notes, either written one after another as in `7b9#11` or
+/// in parentheses as in `7(b9,#11)`.
+fn alterations(input: Span) -> IResult<Span, Vec<Alteration>> {
+    alt((
+        (
+            tag("("),
+            separated_list1(opt(tag(",")), alteration),
+            tag(")"),
+        )
+            .map(|(_, alterations, _)| alterations),
+        many0(alteration),
+    ))
+    .parse(input)
+}
+
+fn alteration(input: Span) -> IResult<Span, Alteration> {
+    (
+        alt((
+            flat.map(|_| Accidental::FLAT),
+            sharp.map(|_| Accidental::SHARP),
+        )),
+        alt((tag("13"), tag("11"), tag("9"), tag("5"))),
+    )
+        .map(|(accidental, degree)| Alteration {
+            degree: degree.parse().unwrap(),
+            accidental,
+        })
+        .parse(input)
+}
+
+fn sus(input: Span) -> IResult<Span, Sus> {
+    alt((
+        tag("sus2").map(|_| Sus::Sus2),
+        tag("sus4").map(|_| Sus::Sus4),
+        tag("sus").map(|_| Sus::Sus4),
+    ))
+    .parse(input)
+}
+
+fn add(input: Span) -> IResult<Span, u8> {
+    (tag("add"), map_res(digit1, |d: Span| d.parse::<u8>()))
+        .map(|(_, degree)| degree)
         .parse(input)
 }
 
@@ -180,6 +852,14 @@ fn scale(input: Span) -> IResult<Span, Scale> {
 }
 
 fn note(input: Span) -> IResult<Span, Note> {
+    if input.extra.lowercase_chords {
+        // `b7` is a scale degree rather than a lowercase B7.
+        return alt((
+            scale_degree.map(Note::Number),
+            letter_note.map(Note::Letter),
+        ))
+        .parse(input);
+    }
     alt((
         letter_note.map(Note::Letter),
         scale_degree.map(Note::Number),
@@ -188,14 +868,28 @@ fn note(input: Span) -> IResult<Span, Note> {
 }
 
 fn letter_note(input: Span) -> IResult<Span, LetterNote> {
+    if input.extra.note_naming == NoteNaming::German {
+        return alt((
+            (tag("H"), accidental).map(|(_, a)| LetterNote(Letter::B, a)),
+            tag("B").map(|_| LetterNote(Letter::B, Accidental::FLAT)),
+            (letter, accidental).map(|(l, a)| LetterNote(l, a)),
+        ))
+        .parse(input);
+    }
+
     (letter, accidental)
         .map(|(l, a)| LetterNote(l, a))
         .parse(input)
 }
 
 fn letter(input: Span) -> IResult<Span, Letter> {
-    one_of("CDEFGAB")
-        .map(|c| match c {
+    let letters = if input.extra.lowercase_chords {
+        "CDEFGABcdefgab"
+    } else {
+        "CDEFGAB"
+    };
+    one_of(letters)
+        .map(|c| match c.to_ascii_uppercase() {
             'C' => Letter::C,
             'D' => Letter::D,
             'E' => Letter::E,
@@ -220,80 +914,480 @@ fn bare_scale_degree(input: Span) -> IResult<Span, u8> {
         .parse(input)
 }
 
+/// Parses the value of a `{tempo}` directive, taking the first number in it as
+/// the beats per minute. A bare number with a `bpm` suffix is treated as just
+/// the number.
+fn tempo_directive(text: &str) -> Option<Directive> {
+    let unitless = text
+        .len()
+        .checked_sub(3)
+        .filter(|&i| text.is_char_boundary(i) && text[i..].eq_ignore_ascii_case("bpm"))
+        .map_or(text, |i| text[..i].trim_end());
+    if let Ok(bpm) = unitless.parse() {
+        return Some(Directive::Tempo { bpm, text: None });
+    }
+
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let digits = &text[start..];
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    let bpm = digits[..end].parse().ok()?;
+    Some(Directive::Tempo {
+        bpm,
+        text: Some(text.to_owned()),
+    })
+}
+
+/// Parses an accidental written in ASCII (`b`, `#`) or with the Unicode
+/// musical symbols (`♭`, `♯`, `𝄫`, `𝄪`), which may be mixed.
 fn accidental(input: Span) -> IResult<Span, Accidental> {
     alt((
-        tag("bb").map(|_| Accidental::DOUBLE_FLAT),
-        tag("b").map(|_| Accidental::FLAT),
-        tag("##").map(|_| Accidental::DOUBLE_SHARP),
-        tag("#").map(|_| Accidental::SHARP),
+        tag("𝄫").map(|_| Accidental::DOUBLE_FLAT),
+        (flat, flat).map(|_| Accidental::DOUBLE_FLAT),
+        flat.map(|_| Accidental::FLAT),
+        tag("𝄪").map(|_| Accidental::DOUBLE_SHARP),
+        (sharp, sharp).map(|_| Accidental::DOUBLE_SHARP),
+        sharp.map(|_| Accidental::SHARP),
         success(Accidental::NATURAL),
     ))
     .parse(input)
 }
 
-impl FromStr for Chart {
-    type Err = String;
+fn flat(input: Span) -> IResult<Span, Span> {
+    alt((tag("b"), tag("♭"))).parse(input)
+}
+
+fn sharp(input: Span) -> IResult<Span, Span> {
+    alt((tag("#"), tag("♯"))).parse(input)
+}
+
+impl Chart {
+    /// Parses a chart with the given options, rather than the defaults used
+    /// by [`str::parse`].
+    pub fn parse_with(input: &str, options: &ChartParseOptions) -> Result<Chart, ParseError> {
+        let joined = if options.extensions {
+            join_continuations(input)
+        } else {
+            Joined::unchanged(input)
+        };
 
-    fn from_str(input: &str) -> Result<Self, Self::Err> {
         chart
-            .parse(Span::new(input))
-            .map(|(_, c)| c)
-            .map_err(|e| e.to_string())
+            .parse(Span::new_extra(&joined.text, *options))
+            .map(|(_, chart)| Chart {
+                source: Source {
+                    positions: chart
+                        .source
+                        .positions
+                        .iter()
+                        .map(|position| position.map(|position| joined.source_position(position)))
+                        .collect(),
+                    line_ending: LineEnding::detect(input),
+                },
+                ..chart
+            })
+            .map_err(|e| ParseError::new(input, &joined, e))
     }
 }
 
-impl FromStr for Scale {
-    type Err = String;
+impl FromStr for Chart {
+    type Err = ParseError;
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        scale
-            .parse(Span::new(input))
-            .map(|(_, s)| s)
-            .map_err(|e| e.to_string())
+        Chart::parse_with(input, &default_options())
     }
 }
 
-impl FromStr for Chord {
-    type Err = String;
+/// A chart that could not be parsed, with where in the source it failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: LineCol,
+    /// The text of the line the error is on.
+    pub source_line: String,
+}
 
-    fn from_str(input: &str) -> Result<Self, Self::Err> {
-        chord
-            .parse(Span::new(input))
-            .map(|(_, c)| c)
-            .map_err(|e| e.to_string())
+impl ParseError {
+    /// Converts an error from parsing `joined`, with positions in the original
+    /// `input`.
+    fn new(input: &str, joined: &Joined, error: nom::Err<Error<Span>>) -> Self {
+        let (message, position) = match error {
+            nom::Err::Error(e) | nom::Err::Failure(e) => {
+                let message = match e.context {
+                    Some(context) => context.to_owned(),
+                    None => format!("unexpected text ({})", e.kind.description().to_lowercase()),
+                };
+                (message, joined.source_position(line_col(&e.input)))
+            }
+            nom::Err::Incomplete(_) => {
+                let position = LineCol {
+                    line: input.lines().count().max(1) as u32,
+                    column: input.lines().last().map_or(0, |line| line.chars().count()) + 1,
+                };
+                ("unexpected end of input".to_owned(), position)
+            }
+        };
+        ParseError {
+            message,
+            position,
+            source_line: input
+                .lines()
+                .nth(position.line as usize - 1)
+                .unwrap_or_default()
+                .to_owned(),
+        }
     }
 }
 
-impl FromStr for LetterNote {
-    type Err = String;
-
-    fn from_str(input: &str) -> Result<Self, Self::Err> {
-        letter_note
-            .parse(Span::new(input))
-            .map(|(_, n)| n)
-            .map_err(|e| e.to_string())
+impl fmt::Display for ParseError {
+    /// Writes the message followed by the line it is on, with a caret under
+    /// the column where the error is.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let LineCol { line, column } = self.position;
+        let before = self
+            .source_line
+            .chars()
+            .take(column - 1)
+            .collect::<String>();
+        writeln!(f, "line {line}, column {column}: {}", self.message)?;
+        writeln!(f, "{}", self.source_line)?;
+        write!(f, "{:1$}^", "", before.width())
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::{
-        chordpro::{
-            charts::{Chart, Chunk, Line},
-            directives::Directive,
-            parser::{Span, directive, set_extensions_enabled},
-        },
-        theory::{
-            chords::Chord,
-            notes::{Accidental, Letter, LetterNote},
-            scales::Scale,
-        },
-    };
+impl std::error::Error for ParseError {}
 
-    use Letter::*;
+/// Parses a songbook of several songs. Each song starts at a `{new_song}`
+/// directive, or at a `{title}` that comes after lyrics or chords of the song
+/// before it.
+pub fn parse_songbook(input: &str, options: &ChartParseOptions) -> Result<Vec<Chart>, ParseError> {
+    parse_iter(input, options).collect()
+}
 
-    const DOUBLE_FLAT: Accidental = Accidental::DOUBLE_FLAT;
-    const FLAT: Accidental = Accidental::FLAT;
+/// Parses a songbook one song at a time, splitting it as [`parse_songbook`]
+/// does. Each song is only parsed when it is reached, so a large songbook
+/// can be processed without holding every chart in memory at once. A song
+/// that fails to parse gives an error, and the songs after it can still be
+/// read.
+pub fn parse_iter<'a>(input: &'a str, options: &ChartParseOptions) -> ChartStream<'a> {
+    ChartStream {
+        rest: input,
+        line: 0,
+        options: *options,
+    }
+}
+
+/// An iterator over the songs in a songbook, from [`parse_iter`].
+#[derive(Debug, Clone)]
+pub struct ChartStream<'a> {
+    rest: &'a str,
+    /// The number of lines before `rest` in the songbook.
+    line: u32,
+    options: ChartParseOptions,
+}
+
+impl<'a> ChartStream<'a> {
+    /// Splits the next song off the front of `rest`, returning its text and
+    /// the number of lines before it.
+    fn next_song(&mut self) -> (&'a str, u32) {
+        let start_line = self.line;
+        let mut end = 0;
+        let mut has_content = false;
+        // The kind of tab or grid section being read, whose lines are kept as
+        // is.
+        let mut verbatim = None;
+        for line in self.rest.split_inclusive('\n') {
+            let content = line.trim_end_matches(['\r', '\n']);
+            match (parse_directive(content, &self.options), verbatim) {
+                (Some(Directive::SectionEnd { kind }), Some(open)) if kind == open => {
+                    verbatim = None;
+                }
+                (_, Some(_)) => {}
+                (Some(Directive::NewSong), None) => {
+                    let song = &self.rest[..end];
+                    self.rest = &self.rest[end + line.len()..];
+                    self.line += 1;
+                    return (song, start_line);
+                }
+                (Some(Directive::Title(_)), None) if has_content => break,
+                (
+                    Some(Directive::SectionStart {
+                        kind: kind @ (SectionKind::Tab | SectionKind::Grid),
+                        ..
+                    }),
+                    None,
+                ) => verbatim = Some(kind),
+                (Some(_), None) => {}
+                (None, None) => has_content |= !content.trim().is_empty(),
+            }
+            end += line.len();
+            self.line += 1;
+        }
+        let song = &self.rest[..end];
+        self.rest = &self.rest[end..];
+        (song, start_line)
+    }
+}
+
+impl Iterator for ChartStream<'_> {
+    type Item = Result<Chart, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.rest.is_empty() {
+            let (song, offset) = self.next_song();
+            let mut chart = match Chart::parse_with(song, &self.options) {
+                Ok(chart) => chart,
+                Err(mut error) => {
+                    error.position.line += offset;
+                    return Some(Err(error));
+                }
+            };
+            // Separators at the start or end of the file leave songs with
+            // nothing in them.
+            if chart.lines.iter().all(Line::is_empty) {
+                continue;
+            }
+            for position in chart.source.positions.iter_mut().flatten() {
+                position.line += offset;
+            }
+            return Some(Ok(chart));
+        }
+        None
+    }
+}
+
+/// Text with continuation lines joined, which remembers where each line of the
+/// original text ended up so that positions can be mapped back to it.
+struct Joined<'a> {
+    text: Cow<'a, str>,
+    /// The position in `text` of the start of each line of the original text.
+    /// Empty if no lines were joined.
+    line_starts: Vec<LineCol>,
+}
+
+impl<'a> Joined<'a> {
+    fn unchanged(input: &'a str) -> Self {
+        Joined {
+            text: Cow::Borrowed(input),
+            line_starts: Vec::new(),
+        }
+    }
+
+    /// Maps a position in the joined text to the same place in the original
+    /// text.
+    fn source_position(&self, position: LineCol) -> LineCol {
+        let Some(index) = self
+            .line_starts
+            .iter()
+            .rposition(|&start| start <= position)
+        else {
+            return position;
+        };
+        LineCol {
+            line: index as u32 + 1,
+            column: position.column - self.line_starts[index].column + 1,
+        }
+    }
+}
+
+/// Joins each line ending in a backslash with the line after it. A line ending
+/// in an even number of backslashes ends in escaped backslashes, and is not
+/// joined. Chord columns in a "chords above" pair are unaffected, as the
+/// continuation is appended to the end of the lyric line.
+fn join_continuations(input: &str) -> Joined<'_> {
+    if !input.contains("\\\n") && !input.contains("\\\r\n") {
+        return Joined::unchanged(input);
+    }
+
+    let mut text = String::with_capacity(input.len());
+    let mut line_starts = Vec::new();
+    let mut position = LineCol { line: 1, column: 1 };
+    for line in input.split_inclusive('\n') {
+        line_starts.push(position);
+        let continued = line
+            .strip_suffix("\\\r\n")
+            .or_else(|| line.strip_suffix("\\\n"))
+            .filter(|continued| {
+                (continued.len() - continued.trim_end_matches('\\').len()) % 2 == 0
+            });
+        match continued {
+            Some(continued) => {
+                text.push_str(continued);
+                position.column += continued.chars().count();
+            }
+            None => {
+                text.push_str(line);
+                position = LineCol {
+                    line: position.line + 1,
+                    column: 1,
+                };
+            }
+        }
+    }
+    Joined {
+        text: Cow::Owned(text),
+        line_starts,
+    }
+}
+
+impl FromStr for Scale {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Scale::parse_with(input, &ChartParseOptions::default())
+    }
+}
+
+impl Scale {
+    /// Parses a key with the note naming from `options`, rather than the
+    /// English names used by [`str::parse`].
+    pub fn parse_with(input: &str, options: &ChartParseOptions) -> Result<Scale, String> {
+        scale
+            .parse(Span::new_extra(input, *options))
+            .map(|(_, s)| s)
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl FromStr for Instrument {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        (instrument, eof)
+            .map(|(i, _)| i)
+            .parse(Span::new_extra(input, ChartParseOptions::default()))
+            .map(|(_, i)| i)
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl FromStr for Chord {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        parse_chord(input, &ChartParseOptions::default())
+    }
+}
+
+impl Chord {
+    /// Parses a whole string as a single chord with `options`, such as
+    /// lowercase chords or German note names.
+    pub fn parse_with(input: &str, options: &ChartParseOptions) -> Result<Chord, String> {
+        parse_chord(input, options)
+    }
+}
+
+/// Parses a whole string as a single chord.
+pub(crate) fn parse_chord(input: &str, options: &ChartParseOptions) -> Result<Chord, String> {
+    let (rest, chord) = chord
+        .parse(Span::new_extra(input, *options))
+        .map_err(|_| format!("{input:?} does not start with a chord"))?;
+    if !rest.is_empty() {
+        return Err(format!(
+            "unexpected {:?} after chord {chord:?} in {input:?}",
+            rest.fragment()
+        ));
+    }
+    Ok(chord)
+}
+
+impl FromStr for StructuredQuality {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        structured_quality
+            .parse(Span::new_extra(input, ChartParseOptions::default()))
+            .map(|(_, q)| q)
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl FromStr for LetterNote {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        letter_note
+            .parse(Span::new_extra(input, ChartParseOptions::default()))
+            .map(|(_, n)| n)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// The parsers used for charts, for reading fragments of ChordPro such as a
+/// single chord or directive. Each takes a `&str` and returns what is left of
+/// it after the parsed value, so they can be combined with other `nom`
+/// parsers.
+pub mod combinators {
+    use nom::IResult;
+
+    use super::Span;
+    use crate::{
+        chordpro::directives::Directive,
+        theory::{
+            chords::Chord,
+            notes::{LetterNote, Note},
+            scales::ScaleDegree,
+        },
+    };
+
+    /// The error returned by each of the parsers, pointing into the input.
+    pub type Error<'input> = nom::error::Error<&'input str>;
+
+    /// A chord without brackets, such as `Am7` or `4/5`.
+    pub fn chord(input: &str) -> IResult<&str, Chord, Error<'_>> {
+        wrap(super::chord, input)
+    }
+
+    /// A note, either a letter such as `Bb` or a scale degree such as `b7`.
+    pub fn note(input: &str) -> IResult<&str, Note, Error<'_>> {
+        wrap(super::note, input)
+    }
+
+    /// A letter with an optional accidental, such as `F#`.
+    pub fn letter_note(input: &str) -> IResult<&str, LetterNote, Error<'_>> {
+        wrap(super::letter_note, input)
+    }
+
+    /// A scale degree with an optional accidental, such as `b3`.
+    pub fn scale_degree(input: &str) -> IResult<&str, ScaleDegree, Error<'_>> {
+        wrap(super::scale_degree, input)
+    }
+
+    /// A directive in braces, such as `{title: Lorem}`.
+    pub fn directive(input: &str) -> IResult<&str, Directive, Error<'_>> {
+        wrap(super::directive, input)
+    }
+
+    fn wrap<'a, O>(
+        parser: fn(Span<'a>) -> super::IResult<Span<'a>, O>,
+        input: &'a str,
+    ) -> IResult<&'a str, O, Error<'a>> {
+        parser(Span::new_extra(input, Default::default()))
+            .map(|(rest, output)| (*rest.fragment(), output))
+            .map_err(|e| e.map(|e| Error::new(*e.input.fragment(), e.kind)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chordpro::{
+            charts::{Chart, ChartDisplayOptions, Chunk, Line, LineCol, LineEnding},
+            directives::{ChordDefinition, CommentStyle, Directive, SectionKind},
+            parser::{ChartParseOptions, Span, combinators, directive, parse_iter, parse_songbook},
+        },
+        theory::{
+            chords::Chord,
+            instruments::Instrument,
+            notes::{Accidental, Letter, LetterNote, Note, NoteNaming},
+            scales::{Scale, ScaleDegree},
+        },
+    };
+
+    use Letter::*;
+
+    const DOUBLE_FLAT: Accidental = Accidental::DOUBLE_FLAT;
+    const FLAT: Accidental = Accidental::FLAT;
     const NATURAL: Accidental = Accidental::NATURAL;
     const SHARP: Accidental = Accidental::SHARP;
     const DOUBLE_SHARP: Accidental = Accidental::DOUBLE_SHARP;
@@ -302,11 +1396,11 @@ mod tests {
     const HOW_GREAT_THOU_ART: &str =
         include_str!("../../examples/How-Great-Thou-Art-(Whakaaria-Mai).chordpro");
     const O_HOLY_NIGHT: &str = include_str!("../../examples/O-Holy-Night-.chordpro");
+    const SONGBOOK: &str = include_str!("../../examples/Songbook.chordpro");
     const TRAILING_CHORDS: &str = include_str!("../../examples/Trailing-Chords.chordpro");
 
     #[test]
     fn test_parse_inline_chart() {
-        set_extensions_enabled(false);
         let chart = HOW_GREAT_THOU_ART.parse::<Chart>().unwrap();
 
         assert_eq!(chart.lines.len(), 34);
@@ -320,7 +1414,7 @@ mod tests {
             chart.lines[5],
             Line::Content {
                 chunks: vec![],
-                inline: true
+                inline: true,
             }
         );
         assert_eq!(
@@ -330,7 +1424,7 @@ mod tests {
                     chord: None,
                     lyrics: "English:".to_owned()
                 }],
-                inline: true
+                inline: true,
             }
         );
         assert_eq!(
@@ -346,7 +1440,7 @@ mod tests {
                         lyrics: "soul".to_owned()
                     }
                 ],
-                inline: true
+                inline: true,
             }
         );
         assert_eq!(
@@ -362,17 +1456,15 @@ mod tests {
                         lyrics: "art".to_owned()
                     }
                 ],
-                inline: true
+                inline: true,
             }
         );
     }
 
     #[test]
     fn test_parse_inline_chart_extensions_enabled() {
-        set_extensions_enabled(true);
-        let chart = HOW_GREAT_THOU_ART.parse::<Chart>().unwrap();
-
-        set_extensions_enabled(false);
+        let chart =
+            Chart::parse_with(HOW_GREAT_THOU_ART, &ChartParseOptions::with_extensions()).unwrap();
         let chart_without_extensions = HOW_GREAT_THOU_ART.parse::<Chart>().unwrap();
 
         assert_eq!(chart, chart_without_extensions);
@@ -380,13 +1472,12 @@ mod tests {
 
     #[test]
     fn test_parse_over_lyrics_chart() {
-        set_extensions_enabled(true);
-        let chart = O_HOLY_NIGHT.parse::<Chart>().unwrap();
+        let chart = Chart::parse_with(O_HOLY_NIGHT, &ChartParseOptions::with_extensions()).unwrap();
 
         assert_eq!(chart.lines.len(), 55);
         assert_eq!(
             chart.lines[0],
-            Line::Directive(Directive::Title("O Holy Night ".to_owned()))
+            Line::Directive(Directive::Title("O Holy Night".to_owned()))
         );
         assert_eq!(
             chart.lines[9],
@@ -395,7 +1486,7 @@ mod tests {
                     chord: None,
                     lyrics: "Intro".to_owned()
                 }],
-                inline: true
+                inline: true,
             }
         );
         assert_eq!(
@@ -419,7 +1510,7 @@ mod tests {
                         lyrics: "".to_owned()
                     },
                 ],
-                inline: false
+                inline: false,
             }
         );
         assert_eq!(
@@ -443,7 +1534,7 @@ mod tests {
                         lyrics: "hining".to_owned()
                     },
                 ],
-                inline: false
+                inline: false,
             }
         );
         assert_eq!(
@@ -453,7 +1544,7 @@ mod tests {
                     chord: None,
                     lyrics: "Chorus 1 ".to_owned()
                 }],
-                inline: true
+                inline: true,
             }
         );
         assert_eq!(
@@ -477,23 +1568,34 @@ mod tests {
                         lyrics: "".to_owned()
                     },
                 ],
-                inline: false
+                inline: false,
             }
         );
     }
 
     #[test]
     fn test_parse_over_lyric_extensions_disabled() {
-        set_extensions_enabled(false);
         let chart = O_HOLY_NIGHT.parse::<Chart>().unwrap();
 
         assert_eq!(chart.lines.len(), 72);
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn test_parse_with_ignores_thread_local() {
+        super::set_extensions_enabled(true);
+        let with_defaults = Chart::parse_with(O_HOLY_NIGHT, &ChartParseOptions::default());
+        let parsed = O_HOLY_NIGHT.parse::<Chart>();
+        super::set_extensions_enabled(false);
+
+        assert_eq!(with_defaults.unwrap().lines.len(), 72);
+        assert_eq!(parsed.unwrap().lines.len(), 55);
+    }
+
     #[test]
     fn test_parse_numbers() {
-        set_extensions_enabled(true);
-        let chart = CHROMATIC_RUN.parse::<Chart>().unwrap();
+        let chart =
+            Chart::parse_with(CHROMATIC_RUN, &ChartParseOptions::with_extensions()).unwrap();
 
         assert_eq!(chart.lines.len(), 5);
         assert_eq!(
@@ -537,15 +1639,15 @@ mod tests {
                         lyrics: "".to_owned()
                     },
                 ],
-                inline: false
+                inline: false,
             }
         );
     }
 
     #[test]
     fn test_parse_trailing_chords() {
-        set_extensions_enabled(true);
-        let chart = TRAILING_CHORDS.parse::<Chart>().unwrap();
+        let chart =
+            Chart::parse_with(TRAILING_CHORDS, &ChartParseOptions::with_extensions()).unwrap();
 
         assert_eq!(chart.lines.len(), 1);
         assert_eq!(
@@ -585,34 +1687,529 @@ mod tests {
                         lyrics: "".to_owned()
                     }
                 ],
-                inline: true
+                inline: true,
             }
         );
     }
 
+    #[test]
+    fn test_parse_positions() {
+        let chart = Chart::parse_with(
+            "{title: Lorem}\n\nC     G\nIpsum dolor\n[Am]sit",
+            &ChartParseOptions::with_extensions(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            (0..chart.lines.len())
+                .map(|index| chart.position(index))
+                .collect::<Vec<_>>(),
+            [1, 2, 3, 5].map(|line| Some(LineCol { line, column: 1 }))
+        );
+        assert_eq!(chart.position(3), Some(LineCol { line: 5, column: 1 }));
+        assert_eq!(chart.position(4), None);
+    }
+
+    #[test]
+    fn test_parse_positions_continuations() {
+        let chart = Chart::parse_with(
+            "[G]Lorem \\\nipsum \\\r\ndolor\n{comment: Sit}\n[C]amet",
+            &ChartParseOptions::with_extensions(),
+        )
+        .unwrap();
+
+        assert_eq!(chart.lines.len(), 3);
+        assert_eq!(
+            (0..chart.lines.len())
+                .map(|index| chart.position(index))
+                .collect::<Vec<_>>(),
+            [1, 4, 5].map(|line| Some(LineCol { line, column: 1 }))
+        );
+    }
+
+    #[test]
+    fn test_parse_tab_verbatim() {
+        let input =
+            "[G]Lorem\n{start_of_tab}\ne|--[0]--3--|\n\nB|--1--[ ]--|\n{end_of_tab}\n[C]ipsum\n";
+        let chart = input.parse::<Chart>().unwrap();
+
+        assert_eq!(chart.lines[2], Line::Verbatim("e|--[0]--3--|".to_owned()));
+        assert_eq!(chart.lines[3], Line::Verbatim("".to_owned()));
+        assert!(matches!(chart.lines[6], Line::Content { .. }));
+        assert_eq!(chart.to_string(), input);
+    }
+
+    #[test]
+    fn test_parse_tab_verbatim_crlf() {
+        let input = "[G]Lorem\n{start_of_tab}\r\ne|--[0]--3--|\r\n\r\n{end_of_tab}\n[C]ipsum\n";
+        let chart = input.parse::<Chart>().unwrap();
+
+        assert_eq!(chart.lines[2], Line::Verbatim("e|--[0]--3--|\r".to_owned()));
+        assert!(chart.lines[3].is_empty());
+        assert_eq!(chart.to_string(), input.replace("}\r\n", "}\n"));
+        let options = ChartDisplayOptions {
+            line_ending: LineEnding::CrLf,
+            ..Default::default()
+        };
+        assert_eq!(
+            chart.display_with(options).to_string(),
+            input.replace("\r\n", "\n").replace('\n', "\r\n")
+        );
+    }
+
+    #[test]
+    fn test_parse_chords_blank_line_lyrics() {
+        let options = ChartParseOptions {
+            skip_blank_after_chords: true,
+            ..ChartParseOptions::with_extensions()
+        };
+        let chart = Chart::parse_with("G      C\n\nLorem  ipsum\nD\n\n[Em]dolor\nA\n\nB", &options)
+            .unwrap();
+
+        assert_eq!(
+            chart.to_string(),
+            "G      C\nLorem  ipsum\nD\n\n[Em]dolor\nA\n\nB\n\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_chords_blank_line_label() {
+        let input = "Intro\nG    D    Em    C\n\nVerse 1\nG       D\nLorem ipsum\n";
+        let chart = Chart::parse_with(input, &ChartParseOptions::with_extensions()).unwrap();
+
+        // The blank line is the empty lyrics of the intro chords, rather than
+        // being skipped to put them over the label.
+        assert_eq!(chart.lines.len(), 4);
+        assert_eq!(chart.lines[2].to_string(), "Verse 1");
+        assert!(chart.to_string().contains("Em C\n\nVerse 1\nG       D\n"));
+        assert_eq!(chart.lyrics_only(), "Intro\n\nVerse 1\nLorem ipsum\n");
+    }
+
+    #[test]
+    fn test_parse_auto_detect_chord_lines() {
+        let options = ChartParseOptions {
+            auto_detect_chord_lines: true,
+            ..Default::default()
+        };
+        let chart = Chart::parse_with("G       C\nAmazing grace", &options).unwrap();
+
+        assert_eq!(chart.to_string(), "G       C\nAmazing grace\n");
+        assert_eq!(chart.chords().count(), 2);
+        assert_eq!(
+            "G       C\nAmazing grace"
+                .parse::<Chart>()
+                .unwrap()
+                .chords()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_parse_auto_detect_chord_lines_false_positives() {
+        let options = ChartParseOptions {
+            auto_detect_chord_lines: true,
+            ..Default::default()
+        };
+        for input in ["Be A Bee\nLorem", "Bad Cab\nLorem", "G C\n{comment: Lorem}"] {
+            let chart = Chart::parse_with(input, &options).unwrap();
+
+            assert_eq!(chart.chords().count(), 0, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_chord_line_false_positives() {
+        let auto_detect = ChartParseOptions {
+            auto_detect_chord_lines: true,
+            ..Default::default()
+        };
+        let input = "Amazing Grace, how sweet\nthe sound";
+        for options in [ChartParseOptions::with_extensions(), auto_detect] {
+            let chart = Chart::parse_with(input, &options).unwrap();
+
+            assert_eq!(chart.chords().count(), 0);
+        }
+        for input in ["Add Fade\nLorem", "Be\nLorem"] {
+            let chart = Chart::parse_with(input, &auto_detect).unwrap();
+
+            assert_eq!(chart.chords().count(), 0, "{input:?}");
+        }
+
+        let input = "A\nLorem";
+        assert_eq!(
+            Chart::parse_with(input, &ChartParseOptions::with_extensions())
+                .unwrap()
+                .chords()
+                .count(),
+            1
+        );
+        assert_eq!(
+            Chart::parse_with(input, &auto_detect)
+                .unwrap()
+                .chords()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_parse_over_lyric_unknown_qualities() {
+        let chart = Chart::parse_with(
+            "G      C2     Dsus\nLorem  ipsum  dolor",
+            &ChartParseOptions::with_extensions(),
+        )
+        .unwrap();
+
+        let chords = chart.chords().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(chords, ["G", "C2", "Dsus"]);
+    }
+
+    #[test]
+    fn test_parse_lowercase_chords() {
+        let lowercase = ChartParseOptions {
+            extensions: true,
+            lowercase_chords: true,
+            ..Default::default()
+        };
+
+        let chart =
+            Chart::parse_with("c       am\nCome to me\n[g]Lorem [e7/b]ipsum", &lowercase).unwrap();
+        assert_eq!(
+            chart.to_string(),
+            "C       Am\nCome to me\n[G]Lorem [E7/B]ipsum\n"
+        );
+
+        let chart = Chart::parse_with("come\nto me", &lowercase).unwrap();
+        assert_eq!(chart.chords().count(), 0);
+        assert!("[c]Lorem".parse::<Chart>().is_err());
+
+        let chart = Chart::parse_with(
+            "[a]Lorem [d7]ipsum [b7]dolor [am]sit [c/e]amet",
+            &ChartParseOptions {
+                lowercase_minor: true,
+                ..lowercase
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            chart.to_string(),
+            "[Am]Lorem [Dm7]ipsum [b7]dolor [Am]sit [Cm/E]amet\n"
+        );
+    }
+
     #[test]
     fn test_parse_directives() {
-        set_extensions_enabled(false);
         let directives = HOW_GREAT_THOU_ART
             .lines()
             .take(5)
-            .map(|input| directive(Span::new(input)).unwrap().1)
+            .map(|input| {
+                directive(Span::new_extra(input, ChartParseOptions::default()))
+                    .unwrap()
+                    .1
+            })
             .collect::<Vec<_>>();
 
         assert_eq!(
             directives,
             vec![
                 Directive::Title("How Great Thou Art (Whakaaria Mai)".to_owned()),
-                Directive::Comment(
-                    "Arrangement: Female Key (Db)  Male Key (Bb)  -  76bpm".to_owned()
-                ),
+                Directive::Comment {
+                    text: "Arrangement: Female Key (Db)  Male Key (Bb)  -  76bpm".to_owned(),
+                    style: CommentStyle::Plain,
+                },
                 Directive::Key(Scale(LetterNote(B, FLAT))),
-                Directive::Tempo(76),
+                Directive::Tempo {
+                    bpm: 76,
+                    text: None
+                },
                 Directive::Other("ccli:7195204".to_owned()),
             ]
         );
     }
 
+    #[test]
+    fn test_parse_directive_names() {
+        for (input, expected) in [
+            ("{ key : Bb }", Directive::Key(Scale(LetterNote(B, FLAT)))),
+            ("{TITLE:Song}", Directive::Title("Song".to_owned())),
+            ("{Title: Song}", Directive::Title("Song".to_owned())),
+            ("{ Capo :2}", Directive::Capo(2)),
+            ("{NP}", Directive::PageBreak { physical: false }),
+            (
+                "{Start_Of_Chorus: Refrain}",
+                Directive::SectionStart {
+                    kind: SectionKind::Chorus,
+                    label: Some("Refrain".to_owned()),
+                },
+            ),
+        ] {
+            assert_eq!(
+                directive(Span::new_extra(input, ChartParseOptions::default()))
+                    .unwrap()
+                    .1,
+                expected,
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_directive_values() {
+        for (input, expected) in [
+            (
+                "{title:O Holy Night }",
+                Directive::Title("O Holy Night".to_owned()),
+            ),
+            (
+                "{title:  O  Holy Night}",
+                Directive::Title("O  Holy Night".to_owned()),
+            ),
+            (
+                "{comment:\tSlowly }",
+                Directive::Comment {
+                    text: "Slowly".to_owned(),
+                    style: CommentStyle::Plain,
+                },
+            ),
+            (
+                "{ci: Slowly}",
+                Directive::Comment {
+                    text: "Slowly".to_owned(),
+                    style: CommentStyle::Italic,
+                },
+            ),
+            (
+                "{Comment_Box:Slowly}",
+                Directive::Comment {
+                    text: "Slowly".to_owned(),
+                    style: CommentStyle::Box,
+                },
+            ),
+            ("{artist:}", Directive::Artist(String::new())),
+        ] {
+            assert_eq!(
+                directive(Span::new_extra(input, ChartParseOptions::default()))
+                    .unwrap()
+                    .1,
+                expected,
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_comment_styles() {
+        let chart = "{cb:Lorem}\n{ci:Ipsum}\n{c:Dolor}\n{comment_italic:Sit}"
+            .parse::<Chart>()
+            .unwrap();
+
+        assert_eq!(chart.comment(), Some("Lorem"));
+        assert_eq!(
+            chart.to_string(),
+            "{comment_box:Lorem}\n{comment_italic:Ipsum}\n{comment:Dolor}\n{comment_italic:Sit}\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_songbook() {
+        let mut charts = parse_songbook(SONGBOOK, &ChartParseOptions::default()).unwrap();
+
+        assert_eq!(charts.len(), 2);
+        assert_eq!(charts[0].title(), Some("Lorem Ipsum"));
+        assert_eq!(charts[0].lines.len(), 6);
+        assert_eq!(charts[1].title(), Some("Sed Do"));
+        assert_eq!(charts[1].lines.len(), 4);
+
+        charts[1].transpose_by(2).unwrap();
+        assert_eq!(
+            charts[1].to_string(),
+            "{title:Sed Do}\n{key:E}\n\n[E]Sed do [A]eiusmod [B]tempor\n"
+        );
+        assert_eq!(charts[0].key(), Some(Scale(G.natural())));
+    }
+
+    #[test]
+    fn test_parse_songbook_titles() {
+        let charts = parse_songbook(
+            "{ns}\n{title:Lorem}\n{subtitle:Ipsum}\n[C]Dolor\n{title:Sit}\n[G]Amet\n{new_song}\n",
+            &ChartParseOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            charts.iter().map(|chart| chart.title()).collect::<Vec<_>>(),
+            [Some("Lorem"), Some("Sit")]
+        );
+        assert_eq!(charts[0].subtitle(), Some("Ipsum"));
+        assert_eq!(charts[1].lines.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_iter() {
+        let mut charts = parse_iter(SONGBOOK, &ChartParseOptions::default());
+
+        let first = charts.next().unwrap().unwrap();
+        assert_eq!(first.title(), Some("Lorem Ipsum"));
+        let second = charts.next().unwrap().unwrap();
+        assert_eq!(second.position(0), Some(LineCol { line: 8, column: 1 }));
+        assert!(charts.next().is_none());
+
+        let songbook = "{title:Lorem}\n[G]Ipsum\n".repeat(1000);
+        assert_eq!(
+            parse_iter(&songbook, &ChartParseOptions::default()).count(),
+            1000
+        );
+    }
+
+    #[test]
+    fn test_parse_iter_error() {
+        let mut charts = parse_iter(
+            "{title:Lorem}\n[G]Ipsum\n{title:Dolor}\n[C]Sit [Amet\n{ns}\n[D]Consectetur",
+            &ChartParseOptions::default(),
+        );
+
+        assert!(charts.next().unwrap().is_ok());
+        assert_eq!(charts.next().unwrap().unwrap_err().position.line, 4);
+        assert_eq!(
+            charts.next().unwrap().unwrap().to_string(),
+            "[D]Consectetur\n"
+        );
+        assert!(charts.next().is_none());
+    }
+
+    #[test]
+    fn test_combinators() {
+        let (rest, chord) = combinators::chord("Am7/G]lorem").unwrap();
+        assert_eq!(rest, "]lorem");
+        assert_eq!(chord.to_string(), "Am7/G");
+
+        let (rest, note) = combinators::note("b7 ipsum").unwrap();
+        assert_eq!(rest, " ipsum");
+        assert_eq!(note, Note::Number(ScaleDegree::new(7, FLAT)));
+
+        let (rest, note) = combinators::letter_note("F#m").unwrap();
+        assert_eq!(rest, "m");
+        assert_eq!(note, LetterNote(F, SHARP));
+
+        let (_, degree) = combinators::scale_degree("#4").unwrap();
+        assert_eq!(degree, ScaleDegree::new(4, SHARP));
+
+        let (rest, directive) = combinators::directive("{title: Lorem}\n").unwrap();
+        assert_eq!(rest, "\n");
+        assert_eq!(directive, Directive::Title("Lorem".to_owned()));
+
+        let nom::Err::Error(error) = combinators::chord("lorem").unwrap_err() else {
+            panic!("expected a recoverable error");
+        };
+        assert_eq!(error.input, "lorem");
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let error = "{title: Lorem}\n{comment: [Ipsum\n[G]Dolor"
+            .parse::<Chart>()
+            .unwrap_err();
+
+        assert_eq!(
+            error.position,
+            LineCol {
+                line: 2,
+                column: 11
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "line 2, column 11: expected a chord\n{comment: [Ipsum\n          ^"
+        );
+
+        let error = "Été [夏天]".parse::<Chart>().unwrap_err();
+        assert!(error.to_string().ends_with("\nÉté [夏天]\n    ^"));
+    }
+
+    #[test]
+    fn test_parse_error_continuations() {
+        let error = Chart::parse_with(
+            "{title: Lorem}\n[G]Ipsum \\\ndolor [Sit\n",
+            &ChartParseOptions::with_extensions(),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.position, LineCol { line: 3, column: 7 });
+        assert_eq!(
+            error.to_string(),
+            "line 3, column 7: expected a chord\ndolor [Sit\n      ^"
+        );
+    }
+
+    #[test]
+    fn test_parse_tempo() {
+        let parse = |input| {
+            directive(Span::new_extra(input, ChartParseOptions::default()))
+                .unwrap()
+                .1
+        };
+
+        assert_eq!(
+            parse("{tempo: 76 (moderate)}"),
+            Directive::Tempo {
+                bpm: 76,
+                text: Some("76 (moderate)".to_owned())
+            }
+        );
+        assert_eq!(
+            parse("{tempo:q=120}"),
+            Directive::Tempo {
+                bpm: 120,
+                text: Some("q=120".to_owned())
+            }
+        );
+        assert_eq!(parse("{tempo:q=120}").to_string(), "{tempo:q=120}");
+        assert_eq!(parse("{tempo: 84}").to_string(), "{tempo:84}");
+        for input in ["{tempo: 76bpm}", "{tempo:76 BPM}"] {
+            assert_eq!(
+                parse(input),
+                Directive::Tempo {
+                    bpm: 76,
+                    text: None
+                }
+            );
+        }
+        assert_eq!(
+            parse("{tempo: ♩=90}"),
+            Directive::Tempo {
+                bpm: 90,
+                text: Some("♩=90".to_owned())
+            }
+        );
+        assert_eq!(
+            parse("{tempo: Moderato}"),
+            Directive::Other("tempo: Moderato".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_parse_time() {
+        let parse = |input| {
+            directive(Span::new_extra(input, ChartParseOptions::default()))
+                .unwrap()
+                .1
+        };
+
+        assert_eq!(parse("{time: 6/8}"), Directive::Time { beats: 6, unit: 8 });
+        assert_eq!(parse("{time: 6/8}").to_string(), "{time:6/8}");
+        assert_eq!(
+            parse("{time:32 / 16}"),
+            Directive::Time {
+                beats: 32,
+                unit: 16
+            }
+        );
+        for input in ["{time: 6/6}", "{time: 0/4}", "{time: 33/4}", "{time: 4}"] {
+            assert!(matches!(parse(input), Directive::Other(_)), "{input}");
+        }
+    }
+
     #[test]
     fn test_parse_letter_note() {
         assert_eq!("C".parse::<LetterNote>().unwrap(), LetterNote(C, NATURAL));
@@ -628,6 +2225,33 @@ mod tests {
         assert_eq!("Db".parse::<LetterNote>().unwrap(), LetterNote(D, FLAT));
     }
 
+    #[test]
+    fn test_parse_unicode_accidentals() {
+        assert_eq!("E♭".parse::<LetterNote>().unwrap(), LetterNote(E, FLAT));
+        assert_eq!("F♯".parse::<LetterNote>().unwrap(), LetterNote(F, SHARP));
+        for double_flat in ["B𝄫", "B♭♭", "Bb♭", "B♭b"] {
+            assert_eq!(
+                double_flat.parse::<LetterNote>().unwrap(),
+                LetterNote(B, DOUBLE_FLAT)
+            );
+        }
+        for double_sharp in ["C𝄪", "C♯♯", "C#♯"] {
+            assert_eq!(
+                double_sharp.parse::<LetterNote>().unwrap(),
+                LetterNote(C, DOUBLE_SHARP)
+            );
+        }
+
+        let chord = "B♭m7".parse::<Chord>().unwrap();
+        assert_eq!(chord.to_string(), "Bbm7");
+
+        let chart = Chart::parse_with(
+            "B      B♭\nLorem  ipsum",
+            &ChartParseOptions::with_extensions(),
+        );
+        assert_eq!(chart.unwrap().to_string(), "B      Bb\nLorem  ipsum\n");
+    }
+
     #[test]
     fn test_parse_scale() {
         assert_eq!("C".parse::<Scale>().unwrap(), Scale(LetterNote(C, NATURAL)));
@@ -642,4 +2266,284 @@ mod tests {
         );
         assert_eq!("Db".parse::<Scale>().unwrap(), Scale(LetterNote(D, FLAT)));
     }
+
+    #[test]
+    fn test_parse_chord_shorthand() {
+        let chart = "{chord: Am frets x02210}\n{define: D frets x x 0 2 3 2}"
+            .parse::<Chart>()
+            .unwrap();
+        assert_eq!(
+            chart.lines[0],
+            Line::Directive(Directive::Chord(ChordDefinition {
+                name: "Am".to_owned(),
+                frets: vec![None, Some(0), Some(2), Some(2), Some(1), Some(0)],
+                fingers: None,
+                compact: true,
+            }))
+        );
+        assert_eq!(
+            chart.lines[1],
+            Line::Directive(Directive::Define(ChordDefinition {
+                name: "D".to_owned(),
+                frets: vec![None, None, Some(0), Some(2), Some(3), Some(2)],
+                fingers: None,
+                compact: false,
+            }))
+        );
+        assert_eq!(
+            chart.to_string(),
+            "{chord:Am frets x02210}\n{define:D frets x x 0 2 3 2}\n"
+        );
+
+        let chart = "{instrument: ukulele}\n{chord: Am frets 2000}"
+            .parse::<Chart>()
+            .unwrap();
+        assert_eq!(
+            chart.lines[0],
+            Line::Directive(Directive::Instrument(Instrument::Ukulele))
+        );
+        assert!(matches!(
+            chart.lines[1],
+            Line::Directive(Directive::Chord(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_chord_definition_fingers() {
+        for (input, compact) in [
+            ("{define: C frets x 3 2 0 1 0 fingers 0 3 2 0 1 0}", false),
+            ("{define: C frets x32010 fingers 032010}", true),
+        ] {
+            let chart = input.parse::<Chart>().unwrap();
+            assert_eq!(
+                chart.chord_definitions().collect::<Vec<_>>(),
+                [&ChordDefinition {
+                    name: "C".to_owned(),
+                    frets: vec![None, Some(3), Some(2), Some(0), Some(1), Some(0)],
+                    fingers: Some(vec![0, 3, 2, 0, 1, 0]),
+                    compact,
+                }]
+            );
+            assert_eq!(chart.to_string(), format!("{}\n", input.replace(": ", ":")));
+        }
+
+        let chart = "{define: C frets x32010 fingers 0320}"
+            .parse::<Chart>()
+            .unwrap();
+        assert_eq!(chart.chord_definitions().count(), 0);
+    }
+
+    #[test]
+    fn test_parse_chord_shorthand_wrong_length() {
+        let chart = "{chord: Am frets 2000}".parse::<Chart>().unwrap();
+        assert_eq!(chart.chord_definitions().count(), 1);
+        assert_eq!(chart.to_string(), "{chord:Am frets 2000}\n");
+
+        let warnings = chart.validate();
+        assert_eq!(warnings[0].line, Some(0));
+        assert_eq!(
+            warnings[0].message,
+            "Am has 4 frets, but the guitar has 6 strings"
+        );
+
+        let chart = "{instrument: ukulele}\n{chord: Am frets x02210}"
+            .parse::<Chart>()
+            .unwrap();
+        assert!(matches!(
+            chart.lines[1],
+            Line::Directive(Directive::Chord(_))
+        ));
+        assert!(
+            chart
+                .validate()
+                .iter()
+                .any(|warning| warning.message == "Am has 6 frets, but the ukulele has 4 strings")
+        );
+    }
+
+    #[test]
+    fn test_parse_continuations() {
+        let chart = Chart::parse_with(
+            "[G]Amazing grace how \\\nsweet the [D]sound\n\
+                     G       C\r\n\
+                     Amazing \\\r\n\
+                     grace\r\n",
+            &ChartParseOptions::with_extensions(),
+        )
+        .unwrap();
+
+        assert_eq!(chart.lines.len(), 2);
+        assert_eq!(
+            chart.lines[0],
+            Line::Content {
+                chunks: vec![
+                    Chunk {
+                        chord: Some(G.natural().major_chord()),
+                        lyrics: "Amazing grace how sweet the ".to_owned()
+                    },
+                    Chunk {
+                        chord: Some(D.natural().major_chord()),
+                        lyrics: "sound".to_owned()
+                    },
+                ],
+                inline: true,
+            }
+        );
+        assert_eq!(
+            chart.lines[1],
+            Line::Content {
+                chunks: vec![
+                    Chunk {
+                        chord: Some(G.natural().major_chord()),
+                        lyrics: "Amazing ".to_owned()
+                    },
+                    Chunk {
+                        chord: Some(C.natural().major_chord()),
+                        lyrics: "grace".to_owned()
+                    },
+                ],
+                inline: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_escaped_backslash_at_end_of_line() {
+        let input = "[G]Lorem \\\\\n[D]Ipsum \\\\\\\ndolor\n";
+        let chart = Chart::parse_with(input, &ChartParseOptions::with_extensions()).unwrap();
+
+        assert_eq!(chart.lines.len(), 2);
+        assert_eq!(chart.chords().count(), 2);
+        assert_eq!(chart.lyrics_only(), "Lorem \\\nIpsum \\dolor\n");
+        assert_eq!(chart.to_string(), "[G]Lorem \\\\\n[D]Ipsum \\\\dolor\n");
+    }
+
+    #[test]
+    fn test_parse_german_names() {
+        let german = ChartParseOptions {
+            note_naming: NoteNaming::German,
+            ..Default::default()
+        };
+        let h7 = Chord::parse_with("H7", &german).unwrap();
+        let b = Chord::parse_with("B", &german).unwrap();
+        let f_over_a = Chord::parse_with("F/A", &german).unwrap();
+
+        assert_eq!(h7.root, B.natural().into());
+        assert_eq!(h7.quality.0, "7");
+        assert_eq!(b, B.flat().major_chord());
+        assert_eq!(f_over_a, F.natural().major_chord().over(A.natural()));
+        assert_eq!(Scale::parse_with("H", &german), Ok(Scale(B.natural())));
+        assert_eq!("B".parse::<Chord>().unwrap(), B.natural().major_chord());
+    }
+
+    #[test]
+    fn test_german_names_round_trip() {
+        let notes = [
+            B.double_flat(),
+            B.flat(),
+            B.natural(),
+            B.sharp(),
+            B.double_sharp(),
+        ];
+        let german = ChartParseOptions {
+            note_naming: NoteNaming::German,
+            ..Default::default()
+        };
+        let round_tripped = notes.map(|n| {
+            let name = n.major_chord().named(NoteNaming::German).to_string();
+            Chord::parse_with(&name, &german)
+        });
+
+        for (note, chord) in notes.into_iter().zip(round_tripped) {
+            assert_eq!(chord.unwrap(), note.major_chord());
+        }
+    }
+
+    #[test]
+    fn test_parse_chord() {
+        for input in ["Bb", "Gm7", "C/E", "F#dim", "1", "b7", "5/7", "#4m7/b3"] {
+            assert_eq!(input.parse::<Chord>().unwrap().to_string(), input);
+        }
+        assert_eq!(
+            "C/E".parse::<Chord>().unwrap(),
+            C.natural().major_chord().over(E.natural())
+        );
+        assert_eq!("b7".parse::<Chord>().unwrap(), Chord::major((7, FLAT)));
+
+        assert_eq!(
+            "H7".parse::<Chord>(),
+            Err("\"H7\" does not start with a chord".to_owned())
+        );
+        assert_eq!(
+            "Gm7 ".parse::<Chord>(),
+            Err("unexpected \" \" after chord Chord(Gm7) in \"Gm7 \"".to_owned())
+        );
+        assert!("C/X".parse::<Chord>().is_err());
+    }
+
+    #[test]
+    fn test_parse_altered_chords() {
+        for (input, quality, canonical) in [
+            ("Dm7b5", "m7b5", "m7b5"),
+            ("C7(#9)", "7(#9)", "7#9"),
+            ("Gø", "ø", "m7b5"),
+            ("Bbø7", "ø7", "m7b5"),
+            ("Co7", "o7", "dim7"),
+            ("G7alt", "7alt", "7alt"),
+            ("E7b9#11", "7b9#11", "7b9#11"),
+            ("A7(b9,b13)", "7(b9,b13)", "7b9b13"),
+        ] {
+            let chord = input.parse::<Chord>().unwrap();
+            assert_eq!(chord.quality.0, quality);
+            assert_eq!(chord.quality.parse().unwrap().to_string(), canonical);
+        }
+
+        let d = "Dm7b5".parse::<Chord>().unwrap();
+        assert_eq!(d.root, D.natural().into());
+        assert_eq!(d.quality.intervals(), [0, 3, 6, 10]);
+        assert_eq!(
+            "Gø".parse::<Chord>().unwrap().quality.intervals(),
+            d.quality.intervals()
+        );
+        assert_eq!(
+            "C7(#9)".parse::<Chord>().unwrap().quality.intervals(),
+            [0, 4, 7, 10, 15]
+        );
+
+        // The accidental of a numbered root or bass note is not an alteration.
+        let numbered = "5b5/b7".parse::<Chord>().unwrap();
+        assert_eq!(numbered.root, Note::Number(ScaleDegree::new(5, NATURAL)));
+        assert_eq!(numbered.quality.0, "b5");
+        assert_eq!(numbered.bass, Some(Note::Number(ScaleDegree::new(7, FLAT))));
+        assert_eq!("5".parse::<Chord>().unwrap().quality.0, "");
+        assert_eq!("Eb".parse::<Chord>().unwrap().quality.0, "");
+
+        // A bare `o` is not diminished, and parentheses must be balanced.
+        for input in ["Go", "Do", "C(", "C)", "C7(b9", "C7b9)"] {
+            assert!(input.parse::<Chord>().is_err(), "{input}");
+        }
+        assert_eq!("Do7".parse::<Chord>().unwrap().quality.0, "o7");
+    }
+
+    #[test]
+    fn test_parse_chord_annotations() {
+        let diamond = "1◊".parse::<Chord>().unwrap();
+        assert_eq!(diamond.root, Note::Number(ScaleDegree::new(1, NATURAL)));
+        assert_eq!(diamond.annotation.as_deref(), Some("◊"));
+
+        let staccato = "4/6.".parse::<Chord>().unwrap();
+        assert_eq!(
+            staccato.bass,
+            Some(Note::Number(ScaleDegree::new(6, NATURAL)))
+        );
+        assert_eq!(staccato.annotation.as_deref(), Some("."));
+        assert_eq!("Cm7".parse::<Chord>().unwrap().annotation, None);
+
+        let mut chart = "{key:G}\n[1◊]Lorem [4/6.]ipsum".parse::<Chart>().unwrap();
+        assert_eq!(chart.to_string(), "{key:G}\n[1◊]Lorem [4/6.]ipsum\n");
+        chart.to_letters().unwrap();
+        assert_eq!(chart.to_string(), "{key:G}\n[G◊]Lorem [C/E.]ipsum\n");
+        chart.transpose_by(2).unwrap();
+        assert_eq!(chart.to_string(), "{key:A}\n[A◊]Lorem [D/F#.]ipsum\n");
+    }
 }
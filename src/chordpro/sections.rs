@@ -0,0 +1,160 @@
+use std::ops::Range;
+
+use crate::chordpro::{
+    charts::{Chart, Line},
+    directives::{Directive, SectionKind},
+};
+
+/// A group of lines between a `{start_of_...}` directive and its matching
+/// `{end_of_...}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section<'a> {
+    pub kind: SectionKind,
+    pub label: Option<&'a str>,
+    /// The indices into [`Chart::lines`] of the lines inside the section,
+    /// excluding the start and end directives.
+    pub lines: Range<usize>,
+}
+
+impl Chart {
+    /// The sections of the chart, in order. A section that is never ended runs
+    /// until the next section starts or the chart ends.
+    pub fn sections(&self) -> impl Iterator<Item = Section<'_>> {
+        let mut sections = Vec::new();
+        let mut current: Option<Section> = None;
+        for (index, line) in self.lines.iter().enumerate() {
+            match line {
+                Line::Directive(Directive::SectionStart { kind, label }) => {
+                    if let Some(mut section) = current.take() {
+                        section.lines.end = index;
+                        sections.push(section);
+                    }
+                    current = Some(Section {
+                        kind: *kind,
+                        label: label.as_deref(),
+                        lines: index + 1..index + 1,
+                    });
+                }
+                Line::Directive(Directive::SectionEnd { kind }) => {
+                    if let Some(mut section) = current.take_if(|s| s.kind == *kind) {
+                        section.lines.end = index;
+                        sections.push(section);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(mut section) = current {
+            section.lines.end = self.lines.len();
+            sections.push(section);
+        }
+        sections.into_iter()
+    }
+
+    /// Replaces each `{chorus}` with a copy of the most recent chorus,
+    /// including its start and end directives. A `{chorus}` before any chorus
+    /// has been written is left as it is.
+    pub fn expand_choruses(&mut self) {
+        let choruses = self
+            .sections()
+            .filter(|section| section.kind == SectionKind::Chorus)
+            .map(|section| {
+                let end = match self.lines.get(section.lines.end) {
+                    Some(Line::Directive(Directive::SectionEnd { .. })) => section.lines.end + 1,
+                    _ => section.lines.end,
+                };
+                section.lines.start - 1..end
+            })
+            .collect::<Vec<_>>();
+
+        let mut lines = Vec::with_capacity(self.lines.len());
+        let mut positions = Vec::with_capacity(self.lines.len());
+        for (index, line) in self.lines.iter().enumerate() {
+            let chorus = choruses.iter().rfind(|chorus| chorus.end <= index);
+            match (line, chorus) {
+                (Line::Directive(Directive::ChorusRecall), Some(chorus)) => {
+                    lines.extend_from_slice(&self.lines[chorus.clone()]);
+                    positions.extend(chorus.clone().map(|index| self.position(index)));
+                }
+                _ => {
+                    lines.push(line.clone());
+                    positions.push(self.position(index));
+                }
+            }
+        }
+        self.lines = lines;
+        self.source.positions = positions;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chordpro::{
+        charts::{Chart, Line},
+        directives::{Directive, SectionKind},
+        sections::Section,
+    };
+
+    #[test]
+    fn test_sections() {
+        let chart = "{start_of_verse: Verse 1}\n[G]Lorem\n[C]ipsum\n{eov}\n\n{soc}\n[D]dolor\n{end_of_chorus}\n{sob}\n[Em]sit"
+            .parse::<Chart>()
+            .unwrap();
+
+        assert_eq!(
+            chart.lines[0],
+            Line::Directive(Directive::SectionStart {
+                kind: SectionKind::Verse,
+                label: Some("Verse 1".to_owned()),
+            })
+        );
+        assert_eq!(
+            chart.lines[3],
+            Line::Directive(Directive::SectionEnd {
+                kind: SectionKind::Verse
+            })
+        );
+        assert_eq!(
+            chart.sections().collect::<Vec<_>>(),
+            vec![
+                Section {
+                    kind: SectionKind::Verse,
+                    label: Some("Verse 1"),
+                    lines: 1..3,
+                },
+                Section {
+                    kind: SectionKind::Chorus,
+                    label: None,
+                    lines: 6..7,
+                },
+                Section {
+                    kind: SectionKind::Bridge,
+                    label: None,
+                    lines: 9..10,
+                },
+            ]
+        );
+        assert_eq!(
+            chart.to_string(),
+            "{start_of_verse:Verse 1}\n[G]Lorem\n[C]ipsum\n{end_of_verse}\n\n{start_of_chorus}\n[D]dolor\n{end_of_chorus}\n{start_of_bridge}\n[Em]sit\n"
+        );
+    }
+
+    #[test]
+    fn test_expand_choruses() {
+        let mut chart = "{chorus}\n{soc}\n[C]Lorem\n[G]ipsum\n{eoc}\n[D]dolor\n{chorus}\n{start_of_chorus: Final}\n[Em]sit\n{eoc}\n{chorus}"
+            .parse::<Chart>()
+            .unwrap();
+        assert_eq!(chart.lines[6], Line::Directive(Directive::ChorusRecall));
+        chart.expand_choruses();
+
+        assert_eq!(
+            chart.to_string(),
+            "{chorus}\n\
+             {start_of_chorus}\n[C]Lorem\n[G]ipsum\n{end_of_chorus}\n[D]dolor\n\
+             {start_of_chorus}\n[C]Lorem\n[G]ipsum\n{end_of_chorus}\n\
+             {start_of_chorus:Final}\n[Em]sit\n{end_of_chorus}\n\
+             {start_of_chorus:Final}\n[Em]sit\n{end_of_chorus}\n"
+        );
+    }
+}
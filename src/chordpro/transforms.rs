@@ -0,0 +1,82 @@
+use crate::{
+    chordpro::charts::{Chart, KeyError},
+    theory::scales::Scale,
+};
+
+/// A single step in a pipeline of changes to a chart. See [`Chart::apply`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transform {
+    TransposeTo(Scale),
+    TransposeBy(i8),
+    ToNumbers,
+    ToLetters,
+    SetInline(bool),
+    QuantizeChords,
+    Simplify,
+    LyricsOnly,
+}
+
+impl Chart {
+    /// Applies each transform in order, stopping at the first that fails.
+    pub fn apply(mut self, transforms: &[Transform]) -> Result<Chart, KeyError> {
+        for transform in transforms {
+            match transform {
+                &Transform::TransposeTo(key) => self.transpose_to(key)?,
+                &Transform::TransposeBy(semitones) => self.transpose_by(semitones)?,
+                Transform::ToNumbers => self.to_numbers()?,
+                Transform::ToLetters => self.to_letters()?,
+                &Transform::SetInline(inline) => self.set_inline(inline),
+                Transform::QuantizeChords => self.quantize_chords(),
+                Transform::Simplify => self.simplify_chords(),
+                Transform::LyricsOnly => self.strip_chords(),
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chordpro::{
+        charts::{Chart, KeyError},
+        transforms::Transform,
+    };
+
+    #[test]
+    fn test_apply() {
+        let chart = "{key:G}\n[G]Lorem [Em7]ipsum [D/F#]dolor"
+            .parse::<Chart>()
+            .unwrap();
+
+        let transposed = chart
+            .clone()
+            .apply(&[Transform::TransposeTo("A".parse().unwrap())])
+            .unwrap();
+        assert_eq!(
+            transposed.to_string(),
+            "{key:A}\n[A]Lorem [F#m7]ipsum [E/G#]dolor\n"
+        );
+
+        let numbers = chart
+            .clone()
+            .apply(&[
+                Transform::TransposeBy(3),
+                Transform::ToNumbers,
+                Transform::Simplify,
+            ])
+            .unwrap();
+        assert_eq!(
+            numbers.to_string(),
+            "{key:Bb}\n[1]Lorem [6m]ipsum [5]dolor\n"
+        );
+
+        let lyrics = chart.apply(&[Transform::LyricsOnly]).unwrap();
+        assert_eq!(lyrics.to_string(), "{key:G}\nLorem ipsum dolor\n");
+
+        let keyless = "[1]Lorem".parse::<Chart>().unwrap();
+        assert_eq!(
+            keyless.apply(&[Transform::ToNumbers]),
+            Err(KeyError::Missing)
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+
+use crate::{
+    chordpro::{
+        charts::{Chart, Line, LineCol},
+        directives::Directive,
+    },
+    theory::{chords::Chord, fingerings::is_same_shape, instruments::Instrument, notes::Note},
+};
+
+/// A problem found in a chart that does not prevent it from being used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// The index into [`Chart::lines`] of the offending line, if the problem
+    /// is with a particular line.
+    pub line: Option<usize>,
+    /// Where the offending line started in the source, if the chart came from
+    /// the parser.
+    pub position: Option<LineCol>,
+    pub message: String,
+}
+
+impl Chart {
+    pub fn validate(&self) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        self.check_key(&mut warnings);
+        self.check_qualities(&mut warnings);
+        self.check_definitions(&mut warnings);
+        self.check_fingerings(&mut warnings);
+        warnings
+    }
+
+    /// Warns about a chart with chords but no key, unless it is declared
+    /// keyless.
+    fn check_key(&self, warnings: &mut Vec<Warning>) {
+        let has_chords = self.lines.iter().any(|line| match line {
+            Line::Content { chunks, .. } => chunks.iter().any(|chunk| chunk.chord.is_some()),
+            Line::Directive(_) | Line::Verbatim(_) => false,
+        });
+        if has_chords && self.key().is_none() && !self.is_declared_keyless() {
+            warnings.push(Warning {
+                line: None,
+                position: None,
+                message: "chart has no key".to_owned(),
+            });
+        }
+    }
+
+    /// Warns about chord qualities that are not understood, such as typos like
+    /// `Gmm`, which otherwise pass through unchanged.
+    fn check_qualities(&self, warnings: &mut Vec<Warning>) {
+        for (index, line) in self.lines.iter().enumerate() {
+            let Line::Content { chunks, .. } = line else {
+                continue;
+            };
+            for chord in chunks.iter().filter_map(|chunk| chunk.chord.as_ref()) {
+                if chord.quality.parse().is_err() {
+                    warnings.push(Warning {
+                        line: Some(index),
+                        position: self.position(index),
+                        message: format!("unknown chord quality {:?} in {chord}", chord.quality.0),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Warns about `{define}` and `{chord}` shapes whose number of frets does
+    /// not match the strings of the instrument in effect.
+    fn check_definitions(&self, warnings: &mut Vec<Warning>) {
+        let mut instrument = Instrument::default();
+        for (index, line) in self.lines.iter().enumerate() {
+            match line {
+                Line::Directive(Directive::Instrument(i)) => instrument = *i,
+                Line::Directive(Directive::Define(definition) | Directive::Chord(definition))
+                    if definition.frets.len() != instrument.string_count() =>
+                {
+                    warnings.push(Warning {
+                        line: Some(index),
+                        position: self.position(index),
+                        message: format!(
+                            "{} has {} frets, but the {instrument} has {} strings",
+                            definition.name,
+                            definition.frets.len(),
+                            instrument.string_count(),
+                        ),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Warns about chords with neither a built-in nor a `{define}`d fingering
+    /// for the selected instrument. Each chord is only reported once.
+    fn check_fingerings(&self, warnings: &mut Vec<Warning>) {
+        let key = self.key();
+        let mut instrument = Instrument::default();
+        let mut defined = Vec::new();
+        let mut reported = Vec::new();
+
+        for (index, line) in self.lines.iter().enumerate() {
+            let chunks = match line {
+                Line::Directive(Directive::Instrument(i)) => {
+                    instrument = *i;
+                    continue;
+                }
+                Line::Directive(Directive::Define(definition) | Directive::Chord(definition)) => {
+                    if definition.frets.len() == instrument.string_count()
+                        && let Ok(chord) = definition.name.parse::<Chord>()
+                    {
+                        defined.push(chord);
+                    }
+                    continue;
+                }
+                Line::Directive(_) | Line::Verbatim(_) => continue,
+                Line::Content { chunks, .. } => chunks,
+            };
+
+            for chord in chunks.iter().filter_map(|chunk| chunk.chord.as_ref()) {
+                let root = match (chord.root, key) {
+                    (Note::Letter(root), _) => root,
+                    (Note::Number(degree), Some(key)) => degree.in_key(key),
+                    (Note::Number(_), None) => continue,
+                };
+                let letter_chord = Chord {
+                    root: Note::Letter(root),
+                    quality: chord.quality.clone(),
+                    bass: None,
+                    annotation: None,
+                };
+
+                let known = instrument.fingering(&letter_chord).is_some()
+                    || defined.iter().any(|d| is_same_shape(d, &letter_chord));
+                if !known && !reported.iter().any(|r| is_same_shape(r, &letter_chord)) {
+                    warnings.push(Warning {
+                        line: Some(index),
+                        position: self.position(index),
+                        message: format!("no {instrument} fingering for {chord}"),
+                    });
+                    reported.push(letter_chord);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.position, self.line) {
+            (Some(LineCol { line, .. }), _) => write!(f, "line {line}: {}", self.message),
+            (None, Some(index)) => write!(f, "line {}: {}", index + 1, self.message),
+            (None, None) => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chordpro::{
+        charts::{Chart, Line, LineCol},
+        directives::Directive,
+        parser::ChartParseOptions,
+        validation::Warning,
+    };
+
+    #[test]
+    fn test_validate_fingerings() {
+        let chart = "{key:G}\n[G]Lorem [Dsus2]ipsum\n{define: Gsus4 frets 330013}\n[Gsus4]dolor [A#]sit [4]amet [Dsus2]"
+            .parse::<Chart>()
+            .unwrap();
+
+        assert_eq!(
+            chart.validate(),
+            vec![Warning {
+                line: Some(1),
+                position: Some(LineCol { line: 2, column: 1 }),
+                message: "no guitar fingering for Dsus2".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_validate_fingerings_other_instruments() {
+        for instrument in ["mandolin", "bass"] {
+            let chart = format!(
+                "{{key:G}}\n{{instrument: {instrument}}}\n[G]Lorem [Em]ipsum [C]dolor [D7]sit [Bbm]amet"
+            )
+            .parse::<Chart>()
+            .unwrap();
+
+            assert_eq!(chart.validate(), vec![], "{instrument}");
+        }
+    }
+
+    #[test]
+    fn test_validate_key() {
+        let chart = "[G]Lorem".parse::<Chart>().unwrap();
+        assert_eq!(
+            chart.validate(),
+            vec![Warning {
+                line: None,
+                position: None,
+                message: "chart has no key".to_owned(),
+            }]
+        );
+
+        let chart = "{key:none}\n[G]Lorem".parse::<Chart>().unwrap();
+        assert_eq!(chart.validate(), vec![]);
+    }
+
+    #[test]
+    fn test_validate_qualities() {
+        let chart = "{key:C}\n[C]Lorem [Cmaj7]ipsum\n[Gmm]dolor [Am7]sit [Cadd]amet"
+            .parse::<Chart>()
+            .unwrap();
+
+        let warnings = chart
+            .validate()
+            .into_iter()
+            .filter(|w| w.message.starts_with("unknown"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            vec![
+                Warning {
+                    line: Some(2),
+                    position: Some(LineCol { line: 3, column: 1 }),
+                    message: "unknown chord quality \"mm\" in Gmm".to_owned(),
+                },
+                Warning {
+                    line: Some(2),
+                    position: Some(LineCol { line: 3, column: 1 }),
+                    message: "unknown chord quality \"add\" in Cadd".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_positions() {
+        let options = ChartParseOptions {
+            extensions: true,
+            ..Default::default()
+        };
+        let mut chart =
+            Chart::parse_with("{key:C}\n[C]Lorem \\\nipsum\n[Gmm]dolor", &options).unwrap();
+        chart.insert_line(0, Line::Directive(Directive::Title("Sit".to_owned())));
+
+        let warnings = chart.validate();
+        assert_eq!(warnings[0].line, Some(3));
+        assert_eq!(warnings[0].position, Some(LineCol { line: 4, column: 1 }));
+        assert_eq!(
+            warnings[0].to_string(),
+            "line 4: unknown chord quality \"mm\" in Gmm"
+        );
+    }
+}
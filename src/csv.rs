@@ -0,0 +1,62 @@
+use std::fmt::Write;
+
+use crate::chordpro::charts::{Chart, Line};
+
+impl Chart {
+    /// Exports every chord in the chart as CSV, one row per chord.
+    pub fn to_csv(&self) -> String {
+        let key = self.key();
+
+        let mut output = String::from("line,chunk,chord,degree,lyrics\n");
+        for (line_index, line) in self.lines.iter().enumerate() {
+            let Line::Content { chunks, .. } = line else {
+                continue;
+            };
+            for (chunk_index, chunk) in chunks.iter().enumerate() {
+                let Some(chord) = &chunk.chord else {
+                    continue;
+                };
+                let degree = key
+                    .map(|key| chord.root.as_scale_degree(key).to_string())
+                    .unwrap_or_default();
+                writeln!(
+                    output,
+                    "{line_index},{chunk_index},{},{},{}",
+                    escape(&chord.to_string()),
+                    escape(&degree),
+                    escape(&chunk.lyrics),
+                )
+                .unwrap();
+            }
+        }
+        output
+    }
+}
+
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chordpro::charts::Chart;
+
+    #[test]
+    fn test_to_csv() {
+        let chart = "{key:G}\n[G]Hello, [Em]world\nNo chords\n[D/F#]\"Bye\""
+            .parse::<Chart>()
+            .unwrap();
+
+        assert_eq!(
+            chart.to_csv(),
+            "line,chunk,chord,degree,lyrics\n\
+             1,0,G,1,\"Hello, \"\n\
+             1,1,Em,6,world\n\
+             3,0,D/F#,5,\"\"\"Bye\"\"\"\n"
+        );
+    }
+}
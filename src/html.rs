@@ -0,0 +1,120 @@
+use std::io::{self, Write};
+
+use crate::chordpro::charts::{Chart, Line};
+
+impl Chart {
+    /// Writes the chart as an HTML fragment to be styled with CSS. Inline lines
+    /// put each chord in a `<ruby>` annotation, while "chords above" lines put
+    /// each chord and its lyrics in a `chunk` span to be stacked with flexbox.
+    pub fn print_to_html(&self, mut f: impl Write) -> io::Result<()> {
+        writeln!(f, r#"<div class="chart">"#)?;
+        if let Some(title) = self.title() {
+            writeln!(f, r#"<h1 class="title">{}</h1>"#, escape(title))?;
+        }
+        if let Some(subtitle) = self.subtitle() {
+            writeln!(f, r#"<p class="subtitle">{}</p>"#, escape(subtitle))?;
+        }
+        if let Some(artist) = self.artist() {
+            writeln!(f, r#"<p class="artist">{}</p>"#, escape(artist))?;
+        }
+        if let Some(comment) = self.comment() {
+            writeln!(f, r#"<p class="comment">{}</p>"#, escape(comment))?;
+        }
+
+        for line in &self.lines {
+            let (chunks, inline) = match line {
+                Line::Content { chunks, inline, .. } => (chunks, inline),
+                Line::Verbatim(text) => {
+                    writeln!(
+                        f,
+                        r#"<pre class="line verbatim">{}</pre>"#,
+                        escape(text.trim_end_matches('\r'))
+                    )?;
+                    continue;
+                }
+                Line::Directive(_) => continue,
+            };
+
+            if *inline {
+                write!(f, r#"<div class="line inline">"#)?;
+            } else {
+                write!(f, r#"<div class="line chords-above">"#)?;
+            }
+            for chunk in chunks {
+                let lyrics = escape(&chunk.lyrics);
+                match (&chunk.chord, inline) {
+                    (None, _) => write!(f, r#"<span class="lyric">{lyrics}</span>"#)?,
+                    (Some(chord), true) => write!(
+                        f,
+                        r#"<ruby><span class="lyric">{lyrics}</span><rt class="chord">{}</rt></ruby>"#,
+                        escape(&chord.to_string())
+                    )?,
+                    (Some(chord), false) => write!(
+                        f,
+                        r#"<span class="chunk"><span class="chord">{}</span><span class="lyric">{lyrics}</span></span>"#,
+                        escape(&chord.to_string())
+                    )?,
+                }
+            }
+            writeln!(f, "</div>")?;
+        }
+
+        writeln!(f, "</div>")?;
+        Ok(())
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chordpro::{charts::Chart, parser::ChartParseOptions};
+
+    const HOW_GREAT_THOU_ART: &str =
+        include_str!("../examples/How-Great-Thou-Art-(Whakaaria-Mai).chordpro");
+    const HOW_GREAT_THOU_ART_HTML: &str = include_str!("../examples/How-Great-Thou-Art.html");
+
+    #[test]
+    fn test_print_to_html() {
+        let chart = HOW_GREAT_THOU_ART.parse::<Chart>().unwrap();
+
+        let mut output = Vec::new();
+        chart.print_to_html(&mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), HOW_GREAT_THOU_ART_HTML);
+    }
+
+    #[test]
+    fn test_print_chords_above_to_html() {
+        let chart = Chart::parse_with(
+            "G      C\nLove & <mercy>",
+            &ChartParseOptions::with_extensions(),
+        );
+
+        let mut output = Vec::new();
+        chart.unwrap().print_to_html(&mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "<div class=\"chart\">\n\
+             <div class=\"line chords-above\">\
+             <span class=\"chunk\"><span class=\"chord\">G</span><span class=\"lyric\">Love &amp; </span></span>\
+             <span class=\"chunk\"><span class=\"chord\">C</span><span class=\"lyric\">&lt;mercy&gt;</span></span>\
+             </div>\n\
+             </div>\n"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt::Write;
+
+use crate::chordpro::{
+    charts::{Chart, Line},
+    directives::{Directive, SectionKind},
+};
+
+/// A verse or chorus that has been opened in the LaTeX output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    Verse,
+    Chorus,
+    /// A verse for lyrics outside any section, which ends at the next blank
+    /// line.
+    ImplicitVerse,
+}
+
+impl Chart {
+    /// Writes the chart as a song for the LaTeX `songs` package. Verses and
+    /// bridges become `\beginverse` blocks and choruses `\beginchorus` blocks,
+    /// while lyrics outside any section are split into verses at blank lines.
+    /// Comments become `\textnote`s, and tab and grid sections are left out.
+    pub fn to_latex(&self) -> String {
+        let mut tex = String::new();
+        let titles = [self.title(), self.subtitle()]
+            .into_iter()
+            .flatten()
+            .map(escape)
+            .collect::<Vec<_>>();
+        write!(tex, r"\beginsong{{{}}}", titles.join(r" \\ ")).unwrap();
+        if let Some(artist) = self.artist() {
+            write!(tex, "[by={{{}}}]", escape(artist)).unwrap();
+        }
+        writeln!(tex).unwrap();
+
+        let mut block = None;
+        for line in &self.lines {
+            match line {
+                Line::Directive(Directive::SectionStart { kind, .. }) => {
+                    close(&mut tex, &mut block);
+                    block = match kind {
+                        SectionKind::Chorus => Some(Block::Chorus),
+                        SectionKind::Verse | SectionKind::Bridge => Some(Block::Verse),
+                        SectionKind::Tab | SectionKind::Grid => None,
+                    };
+                    match block {
+                        Some(Block::Chorus) => writeln!(tex, r"\beginchorus").unwrap(),
+                        Some(_) => writeln!(tex, r"\beginverse").unwrap(),
+                        None => {}
+                    }
+                }
+                Line::Directive(Directive::SectionEnd { .. }) => close(&mut tex, &mut block),
+                Line::Directive(Directive::Comment { text, .. }) => {
+                    writeln!(tex, r"\textnote{{{}}}", escape(text)).unwrap();
+                }
+                Line::Directive(Directive::ChorusRecall) => {
+                    writeln!(tex, r"\textnote{{Chorus}}").unwrap();
+                }
+                Line::Directive(_) | Line::Verbatim(_) => {}
+                Line::Content { .. } if line.is_empty() => {
+                    if block == Some(Block::ImplicitVerse) {
+                        close(&mut tex, &mut block);
+                    }
+                }
+                Line::Content { chunks, .. } => {
+                    if block.is_none() {
+                        writeln!(tex, r"\beginverse").unwrap();
+                        block = Some(Block::ImplicitVerse);
+                    }
+                    for chunk in chunks {
+                        if let Some(chord) = &chunk.chord {
+                            // The songs package writes flats as `&`.
+                            write!(tex, r"\[{}]", chord.to_string().replace('b', "&")).unwrap();
+                        }
+                        write!(tex, "{}", escape(&chunk.lyrics)).unwrap();
+                    }
+                    writeln!(tex).unwrap();
+                }
+            }
+        }
+        close(&mut tex, &mut block);
+        writeln!(tex, r"\endsong").unwrap();
+        tex
+    }
+}
+
+fn close(tex: &mut String, block: &mut Option<Block>) {
+    match block.take() {
+        Some(Block::Chorus) => writeln!(tex, r"\endchorus").unwrap(),
+        Some(Block::Verse | Block::ImplicitVerse) => writeln!(tex, r"\endverse").unwrap(),
+        None => {}
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str(r"\textbackslash{}"),
+            '~' => escaped.push_str(r"\textasciitilde{}"),
+            '^' => escaped.push_str(r"\textasciicircum{}"),
+            '&' | '%' | '#' | '_' | '$' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chordpro::charts::Chart;
+
+    const SECTIONS: &str = include_str!("../examples/Sections.chordpro");
+    const SECTIONS_TEX: &str = include_str!("../examples/Sections.tex");
+
+    #[test]
+    fn test_to_latex() {
+        let chart = SECTIONS.parse::<Chart>().unwrap();
+
+        assert_eq!(chart.to_latex(), SECTIONS_TEX);
+    }
+
+    #[test]
+    fn test_to_latex_escapes() {
+        let chart = "{title:Lorem & Ipsum}\n{artist:Dolor_Sit}\n[Bb]100% [F#m7b5]#1\n\n[Eb]amet"
+            .parse::<Chart>()
+            .unwrap();
+
+        assert_eq!(
+            chart.to_latex(),
+            "\\beginsong{Lorem \\& Ipsum}[by={Dolor\\_Sit}]\n\
+             \\beginverse\n\\[B&]100\\% \\[F#m7&5]\\#1\n\\endverse\n\
+             \\beginverse\n\\[E&]amet\n\\endverse\n\
+             \\endsong\n"
+        );
+    }
+}
@@ -1,5 +1,13 @@
 pub mod chordpro;
+pub mod csv;
+pub mod html;
+pub mod latex;
+pub mod print;
+pub mod text;
 pub mod theory;
 
-#[cfg(feature = "print")]
-pub mod print;
+#[cfg(feature = "audio")]
+pub mod audio;
+
+#[cfg(feature = "midi")]
+pub mod midi;
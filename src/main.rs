@@ -2,8 +2,11 @@ use std::{fs, path::PathBuf};
 
 use clap::Parser;
 use diameter::{
-    chordpro::{charts::Chart, parser::set_extensions_enabled},
-    theory::scales::Scale,
+    chordpro::{
+        charts::{Chart, ChartDisplayOptions, LineEnding},
+        parser::ChartParseOptions,
+    },
+    theory::{notes::NoteNaming, scales::Scale},
 };
 
 #[derive(Parser)]
@@ -17,15 +20,29 @@ struct Cli {
     #[arg(short, long)]
     #[cfg(feature = "print")]
     pdf_output: Option<PathBuf>,
+    /// Draw a diagram of each chord at the top of the PDF
+    #[arg(short = 'D', long)]
+    #[cfg(feature = "print")]
+    diagrams: bool,
+    /// Draw a vertical rule down the left of each section in the PDF
+    #[arg(short = 'R', long)]
+    #[cfg(feature = "print")]
+    section_rules: bool,
     /// Enable non-standard extensions when parsing (e.g. "chords above" format)
     #[arg(short = 'x', long)]
     extensions: bool,
+    /// With -x, skip a single blank line between a row of chords and its lyrics
+    #[arg(short = 'b', long)]
+    skip_blank_after_chords: bool,
     /// Output chords using "chords above" format
     #[arg(short = 'v', long)]
     chords_above: bool,
+    /// Use German note names (H for B natural, B for B flat)
+    #[arg(short = 'G', long)]
+    german: bool,
     /// Transpose the song into a different key
     #[arg(short, long)]
-    key: Option<Scale>,
+    key: Option<String>,
     /// Convert letter chords to numbers
     #[arg(short, long)]
     numbers: bool,
@@ -33,35 +50,72 @@ struct Cli {
 
 fn main() {
     let cli = Cli::parse();
-    set_extensions_enabled(cli.extensions);
+    let note_naming = if cli.german {
+        NoteNaming::German
+    } else {
+        NoteNaming::English
+    };
 
     let input = fs::read_to_string(&cli.input).expect("unable to read input file");
-    let mut chart = input
-        .parse::<Chart>()
-        .expect("unable to parse ChordPro file");
+    let options = ChartParseOptions {
+        extensions: cli.extensions,
+        skip_blank_after_chords: cli.skip_blank_after_chords,
+        note_naming,
+        ..Default::default()
+    };
+    // The key is parsed here rather than by clap, so that it uses the same
+    // note names as the chart.
+    let new_key = cli.key.map(|key| {
+        Scale::parse_with(&key, &options).unwrap_or_else(|e| {
+            eprintln!("invalid key {key:?}: {e}");
+            std::process::exit(1);
+        })
+    });
+    let mut chart = Chart::parse_with(&input, &options).unwrap_or_else(|e| {
+        eprintln!("unable to parse ChordPro file: {e}");
+        std::process::exit(1);
+    });
 
     chart.set_inline(!cli.chords_above);
-    if let Some(new_key) = cli.key {
-        chart.transpose_to(new_key);
+    if let Some(new_key) = new_key {
+        chart.transpose_to(new_key).expect("unable to transpose");
     }
     if cli.numbers {
-        chart.to_numbers();
+        chart.to_numbers().expect("unable to convert to numbers");
     }
 
     let mut did_output = false;
     if let Some(output) = cli.output {
-        fs::write(output, chart.to_string()).expect("unable to write output file");
+        // Keep the line endings of the input, so that the output does not
+        // differ from it on every line.
+        let options = ChartDisplayOptions {
+            line_ending: LineEnding::Detect,
+            note_naming,
+            ..Default::default()
+        };
+        fs::write(output, chart.display_with(options).to_string())
+            .expect("unable to write output file");
         did_output = true;
     }
     #[cfg(feature = "print")]
     if let Some(pdf_output) = cli.pdf_output {
+        let options = ChartDisplayOptions {
+            show_diagrams: cli.diagrams,
+            section_rules: cli.section_rules,
+            note_naming,
+            ..Default::default()
+        };
         chart
-            .print_to_pdf(&pdf_output)
+            .print_to_pdf_with(&pdf_output, options)
             .expect("unable to print to PDF");
         did_output = true;
     }
 
     if !did_output {
-        print!("{chart}");
+        let options = ChartDisplayOptions {
+            note_naming,
+            ..Default::default()
+        };
+        print!("{}", chart.display_with(options));
     }
 }
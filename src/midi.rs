@@ -0,0 +1,145 @@
+use std::io::{self, Write};
+
+use crate::{
+    chordpro::charts::Chart,
+    theory::{notes::Letter, scales::Scale},
+};
+
+/// Ticks per quarter note.
+const DIVISION: u16 = 480;
+const VELOCITY: u8 = 80;
+const DEFAULT_TEMPO: u32 = 120;
+
+impl Chart {
+    /// Writes the chart as a Standard MIDI File of block chords, each held for
+    /// a quarter note, with a rest for each chunk that has no chord. Numbered
+    /// chords are resolved in the chart's key (or the detected key, or C), and
+    /// the tempo and time signature come from the `{tempo}` and `{time}`
+    /// directives, defaulting to 120 BPM and leaving out a time signature
+    /// whose beat unit is not a power of two.
+    pub fn export_midi(&self, mut f: impl Write) -> io::Result<()> {
+        let key = self
+            .key()
+            .or_else(|| self.detect_key())
+            .unwrap_or(Scale(Letter::C.natural()));
+        let tempo = self.tempo().unwrap_or(DEFAULT_TEMPO).max(1);
+
+        let mut track = Vec::new();
+        let microseconds_per_quarter = 60_000_000 / tempo;
+        track.extend([0x00, 0xFF, 0x51, 0x03]);
+        track.extend(&microseconds_per_quarter.to_be_bytes()[1..]);
+        if let Some((beats, unit)) = self.time_signature()
+            && unit.is_power_of_two()
+        {
+            // 24 MIDI clocks per metronome click and 8 thirty-second notes per
+            // quarter note, as in most files.
+            track.extend([0x00, 0xFF, 0x58, 0x04, beats, unit.ilog2() as u8, 24, 8]);
+        }
+
+        let mut delta = 0;
+        for chunk in self.content_lines().flatten() {
+            let Some(chord) = &chunk.chord else {
+                delta += DIVISION as u32;
+                continue;
+            };
+
+            let pitches = chord
+                .pitches(key)
+                .into_iter()
+                .map(|pitch| pitch.as_int().clamp(0, 127) as u8)
+                .collect::<Vec<_>>();
+            for &pitch in &pitches {
+                write_variable_length(&mut track, delta);
+                track.extend([0x90, pitch, VELOCITY]);
+                delta = 0;
+            }
+            delta += DIVISION as u32;
+            for &pitch in &pitches {
+                write_variable_length(&mut track, delta);
+                track.extend([0x80, pitch, 0]);
+                delta = 0;
+            }
+        }
+        write_variable_length(&mut track, delta);
+        track.extend([0xFF, 0x2F, 0x00]);
+
+        f.write_all(b"MThd")?;
+        f.write_all(&6u32.to_be_bytes())?;
+        f.write_all(&0u16.to_be_bytes())?;
+        f.write_all(&1u16.to_be_bytes())?;
+        f.write_all(&DIVISION.to_be_bytes())?;
+        f.write_all(b"MTrk")?;
+        f.write_all(&(track.len() as u32).to_be_bytes())?;
+        f.write_all(&track)
+    }
+}
+
+fn write_variable_length(out: &mut Vec<u8>, value: u32) {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    let mut value = value >> 7;
+    while value > 0 {
+        bytes.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.extend(bytes.iter().rev());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chordpro::{builder::ChartBuilder, charts::Chart, directives::Directive};
+
+    #[test]
+    fn test_export_midi() {
+        let chart = "{tempo: 100}\nLorem [C]ipsum [Am]dolor"
+            .parse::<Chart>()
+            .unwrap();
+        let mut midi = Vec::new();
+        chart.export_midi(&mut midi).unwrap();
+
+        assert_eq!(&midi[..14], b"MThd\0\0\0\x06\0\0\0\x01\x01\xe0", "header");
+        assert_eq!(&midi[14..18], b"MTrk");
+        let track = &midi[22..];
+        assert_eq!(
+            track.len() as u32,
+            u32::from_be_bytes(midi[18..22].try_into().unwrap())
+        );
+
+        // 600,000 microseconds per quarter note is 100 BPM
+        assert_eq!(&track[..7], [0x00, 0xFF, 0x51, 0x03, 0x09, 0x27, 0xC0]);
+        // C major after a quarter note rest for "Lorem "
+        assert_eq!(&track[7..11], [0x83, 0x60, 0x90, 60]);
+        assert_eq!(&track[11..17], [80, 0x00, 0x90, 64, 80, 0x00]);
+        assert_eq!(&track[17..19], [0x90, 67]);
+        // A minor starts as soon as C major is released
+        assert_eq!(
+            &track[33..45],
+            [0x00, 0x90, 69, 80, 0x00, 0x90, 72, 80, 0x00, 0x90, 76, 80]
+        );
+        assert_eq!(&track[track.len() - 3..], [0xFF, 0x2F, 0x00]);
+    }
+
+    #[test]
+    fn test_export_midi_time_signature() {
+        let chart = "{time: 6/8}\n[C]Lorem".parse::<Chart>().unwrap();
+        let mut midi = Vec::new();
+        chart.export_midi(&mut midi).unwrap();
+
+        let track = &midi[22..];
+        assert_eq!(&track[7..15], [0x00, 0xFF, 0x58, 0x04, 6, 3, 24, 8]);
+    }
+
+    #[test]
+    fn test_export_midi_invalid_time_signature() {
+        for unit in [0, 6] {
+            let chart = ChartBuilder::new()
+                .directive(Directive::Time { beats: 4, unit })
+                .lyrics("Lorem")
+                .build();
+            let mut midi = Vec::new();
+            chart.export_midi(&mut midi).unwrap();
+
+            let track = &midi[22..];
+            assert_ne!(&track[7..10], [0x00, 0xFF, 0x58], "{unit}");
+        }
+    }
+}
@@ -1,13 +1,29 @@
+use std::io::{self, Write};
+#[cfg(feature = "print")]
 use std::{
-    io::{self, Write},
     path::Path,
     process::{Command, Stdio},
 };
 
-use crate::chordpro::charts::{Chart, Line};
+use crate::{
+    chordpro::{
+        charts::{Chart, ChartDisplayOptions, Line},
+        directives::{CommentStyle, Directive},
+    },
+    text::capitalize,
+    theory::notes::NoteNaming,
+};
 
 impl Chart {
+    /// Prints the chart to a PDF file by running the `typst` command, which
+    /// must be installed.
+    #[cfg(feature = "print")]
     pub fn print_to_pdf(&self, output: &Path) -> io::Result<()> {
+        self.print_to_pdf_with(output, ChartDisplayOptions::default())
+    }
+
+    #[cfg(feature = "print")]
+    pub fn print_to_pdf_with(&self, output: &Path, options: ChartDisplayOptions) -> io::Result<()> {
         let mut child = Command::new("typst")
             .arg("compile")
             .arg("-")
@@ -19,7 +35,7 @@ impl Chart {
             .stdin
             .take()
             .ok_or_else(|| io::Error::other("unable to open stdin of child process"))?;
-        self.print_to_typst(&mut stdin)?;
+        self.print_to_typst_with(&mut stdin, options)?;
         drop(stdin);
 
         let status = child.wait()?;
@@ -32,27 +48,111 @@ impl Chart {
         Ok(())
     }
 
-    pub fn print_to_typst(&self, mut f: impl Write) -> io::Result<()> {
+    pub fn print_to_typst(&self, f: impl Write) -> io::Result<()> {
+        self.print_to_typst_with(f, ChartDisplayOptions::default())
+    }
+
+    pub fn print_to_typst_with(
+        &self,
+        mut f: impl Write,
+        options: ChartDisplayOptions,
+    ) -> io::Result<()> {
         writeln!(f, r#"#import "@preview/chordx:0.6.1": single-chord"#)?;
 
         writeln!(f, r#"#set text(font: "Arial")"#)?;
         if let Some(title) = &self.title() {
             writeln!(f, "= {title}")?;
         }
-        if let Some(comment) = &self.comment() {
-            writeln!(f, "{comment}")?;
+        if let Some(subtitle) = &self.subtitle() {
+            writeln!(f, r"{subtitle}\")?;
+        }
+        if let Some(artist) = &self.artist() {
+            writeln!(f, r"{artist}\")?;
+        }
+        let key = self.key().map(|key| {
+            let key = key.0.named(options.note_naming).to_string();
+            format!("Key: {}", typst_string(&key))
+        });
+        let tempo = self.tempo().map(|tempo| format!("Tempo: {tempo}"));
+        let key_and_tempo = [key, tempo].into_iter().flatten().collect::<Vec<_>>();
+        if !key_and_tempo.is_empty() {
+            writeln!(f, r"{}\", key_and_tempo.join(" #h(2em) "))?;
+        }
+        // Comments before the first lyrics or chords are shown with the title,
+        // and the rest where they occur.
+        let body_start = self
+            .lines
+            .iter()
+            .position(|line| matches!(line, Line::Content { .. }) && !line.is_empty())
+            .unwrap_or(self.lines.len());
+        let header_comments = self.lines[..body_start]
+            .iter()
+            .filter_map(|line| match line {
+                Line::Directive(Directive::Comment { text, .. }) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if !header_comments.is_empty() {
+            writeln!(f, "{}", header_comments.join("\\\n"))?;
         }
 
         writeln!(f, r#"#set text(font: "Courier New")"#)?;
         writeln!(f, r#"#let chord = single-chord.with(weight: "semibold")"#)?;
+        if options.show_diagrams {
+            self.print_chord_diagrams(&mut f, options.note_naming)?;
+        }
+
+        let columns = self.columns().unwrap_or(1);
+        if columns > 1 {
+            writeln!(f, "#columns({columns})[")?;
+        }
 
-        for line in &self.lines {
+        let mut in_rule = false;
+        for (i, line) in self.lines.iter().enumerate() {
+            // Sections that are not closed end at the next one.
+            if in_rule
+                && matches!(
+                    line,
+                    Line::Directive(Directive::SectionStart { .. } | Directive::SectionEnd { .. })
+                )
+            {
+                writeln!(f, "]")?;
+                in_rule = false;
+            }
+            if options.section_rules
+                && matches!(line, Line::Directive(Directive::SectionStart { .. }))
+            {
+                writeln!(f, "#block(stroke: (left: 0.5pt), inset: (left: 0.5em))[")?;
+                in_rule = true;
+            }
             match line {
+                Line::Directive(Directive::ColumnBreak) => writeln!(f, "#colbreak()")?,
+                Line::Directive(Directive::PageBreak { .. }) => writeln!(f, "#pagebreak()")?,
+                Line::Directive(Directive::SectionStart {
+                    label: Some(label), ..
+                }) => writeln!(f, r"*{}*\", typst_string(label))?,
+                Line::Directive(Directive::SectionStart { kind, label: None }) => {
+                    writeln!(f, r"*{}*\", capitalize(&kind.to_string()))?
+                }
+                Line::Directive(Directive::ChorusRecall) => writeln!(f, r"_Chorus_\")?,
+                Line::Directive(Directive::Comment { text, style }) if i >= body_start => {
+                    let text = typst_string(text);
+                    match style {
+                        CommentStyle::Plain | CommentStyle::Italic => writeln!(f, r"_{text}_\")?,
+                        CommentStyle::Box => {
+                            writeln!(f, r"#box(stroke: 0.5pt, inset: 3pt)[{text}]\")?
+                        }
+                    }
+                }
                 Line::Directive(_) => {}
-                Line::Content { chunks, inline: _ } => {
+                Line::Verbatim(text) => {
+                    writeln!(f, r"{}\", typst_string(text.trim_end_matches('\r')))?;
+                }
+                Line::Content { chunks, .. } => {
                     for chunk in chunks {
                         let lyrics = &chunk.lyrics;
                         if let Some(chord) = &chunk.chord {
+                            let chord = chord.named(options.note_naming);
                             let offset = if !lyrics.trim().is_empty() { "1" } else { "" };
                             write!(f, r#"#chord[#"{lyrics}"][#"{chord} "][{offset}]"#)?;
                         } else {
@@ -64,17 +164,99 @@ impl Chart {
             }
         }
 
+        if in_rule {
+            writeln!(f, "]")?;
+        }
+        if columns > 1 {
+            writeln!(f, "]")?;
+        }
+
+        Ok(())
+    }
+
+    /// Draws a diagram of each chord in the song with the `chart-chord`
+    /// function from chordx. Shapes from `{define}` or `{chord}` that fit the
+    /// instrument take precedence over the built-in fingerings, and chords
+    /// with neither are left out. So are
+    /// shapes above the ninth fret, as chordx takes one character per string.
+    fn print_chord_diagrams(&self, mut f: impl Write, naming: NoteNaming) -> io::Result<()> {
+        let instrument = self
+            .lines
+            .iter()
+            .find_map(|line| match line {
+                &Line::Directive(Directive::Instrument(instrument)) => Some(instrument),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        let diagrams = self
+            .unique_chords()
+            .iter()
+            .filter_map(|chord| {
+                let name = chord.named(naming).to_string();
+                let definition = self
+                    .chord_definitions()
+                    .find(|d| d.name == name && d.frets.len() == instrument.string_count());
+                match definition {
+                    Some(definition) => {
+                        Some((name, definition.frets.clone(), definition.fingers.clone()))
+                    }
+                    None => Some((name, instrument.fingering(chord)?, None)),
+                }
+            })
+            .filter(|(_, frets, _)| frets.iter().flatten().all(|&fret| fret <= 9))
+            .map(|(name, frets, fingers)| {
+                let tabs = frets
+                    .iter()
+                    .map(|fret| match fret {
+                        None => 'x',
+                        Some(0) => 'o',
+                        Some(fret) => (b'0' + fret) as char,
+                    })
+                    .collect::<String>();
+                let fingers = match fingers {
+                    Some(fingers) => fingers
+                        .iter()
+                        .map(|&finger| match finger {
+                            0 => 'n',
+                            finger => (b'0' + finger.min(9)) as char,
+                        })
+                        .collect::<String>(),
+                    None => "n".repeat(tabs.len()),
+                };
+                // The name is written as a string, so that the `#` of a sharp
+                // is not read as Typst code.
+                let name = typst_string(&name);
+                format!(r#"#chart-chord(tabs: "{tabs}", fingers: "{fingers}")[{name}]"#)
+            })
+            .collect::<Vec<_>>();
+
+        if !diagrams.is_empty() {
+            writeln!(f, r#"#import "@preview/chordx:0.6.1": chart-chord"#)?;
+            writeln!(f, "{}", diagrams.join(" "))?;
+            writeln!(f)?;
+        }
         Ok(())
     }
 }
 
+/// Writes text as a Typst string literal, so that markup characters such as
+/// `*`, `_`, `#` and `]` are printed as they are.
+fn typst_string(text: &str) -> String {
+    format!(r#"#"{}""#, text.replace('\\', r"\\").replace('"', r#"\""#))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::chordpro::charts::Chart;
+    use crate::chordpro::charts::{Chart, ChartDisplayOptions};
 
     const HOW_GREAT_THOU_ART: &str =
         include_str!("../examples/How-Great-Thou-Art-(Whakaaria-Mai).chordpro");
     const HOW_GREAT_THOU_ART_TYPST: &str = include_str!("../examples/How-Great-Thou-Art.typst");
+    const SECTIONS: &str = include_str!("../examples/Sections.chordpro");
+    const SECTIONS_TYPST: &str = include_str!("../examples/Sections.typst");
+    const CUSTOM_CHORD: &str = include_str!("../examples/Custom-Chord.chordpro");
+    const CUSTOM_CHORD_TYPST: &str = include_str!("../examples/Custom-Chord.typst");
 
     #[test]
     fn test_print_to_typst() {
@@ -85,4 +267,170 @@ mod tests {
 
         assert_eq!(String::from_utf8(output).unwrap(), HOW_GREAT_THOU_ART_TYPST);
     }
+
+    #[test]
+    fn test_print_sections_to_typst() {
+        let chart = SECTIONS.parse::<Chart>().unwrap();
+
+        let mut output = Vec::new();
+        chart.print_to_typst(&mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), SECTIONS_TYPST);
+    }
+
+    #[test]
+    fn test_print_artist_to_typst() {
+        let chart = "{title:Lorem}\n{subtitle:Ipsum}\n{artist:Dolor}\n{comment:Sit}"
+            .parse::<Chart>()
+            .unwrap();
+
+        let mut output = Vec::new();
+        chart.print_to_typst(&mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("= Lorem\nIpsum\\\nDolor\\\nSit\n"));
+    }
+
+    #[test]
+    fn test_print_key_and_tempo_to_typst() {
+        let print = |input: &str| {
+            let mut output = Vec::new();
+            input
+                .parse::<Chart>()
+                .unwrap()
+                .print_to_typst(&mut output)
+                .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        assert!(print("{title:Lorem}\n{tempo:96}\nIpsum").contains("= Lorem\nTempo: 96\\\n"));
+        assert!(print("{title:Lorem}\n{key:F#}\nIpsum").contains("= Lorem\nKey: #\"F#\"\\\n"));
+
+        let output = print("{title:Lorem}\nIpsum");
+        assert!(!output.contains("Key:"));
+        assert!(!output.contains("Tempo:"));
+    }
+
+    #[test]
+    fn test_print_columns_to_typst() {
+        let chart = "{columns: 2}\nLorem\n{column_break}\nIpsum"
+            .parse::<Chart>()
+            .unwrap();
+
+        let mut output = Vec::new();
+        chart.print_to_typst(&mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with("#columns(2)[\nLorem\\\n#colbreak()\nIpsum\\\n]\n"));
+    }
+
+    #[test]
+    fn test_print_page_breaks_to_typst() {
+        let chart = "{start_of_verse: Verse 1}\nLorem\n{end_of_verse}\n{new_page}\nIpsum"
+            .parse::<Chart>()
+            .unwrap();
+
+        let mut output = Vec::new();
+        chart.print_to_typst(&mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with("*#\"Verse 1\"*\\\nLorem\\\n#pagebreak()\nIpsum\\\n"));
+    }
+
+    #[test]
+    fn test_print_markup_in_labels_and_comments_to_typst() {
+        let chart =
+            "{start_of_verse: *Verse_1]}\nLorem\n{end_of_verse}\n{comment: \"Ipsum\" [x2] \\ *}"
+                .parse::<Chart>()
+                .unwrap();
+
+        let mut output = Vec::new();
+        chart.print_to_typst(&mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(
+            output.ends_with("*#\"*Verse_1]\"*\\\nLorem\\\n_#\"\\\"Ipsum\\\" [x2] \\\\ *\"_\\\n")
+        );
+    }
+
+    #[test]
+    fn test_print_section_rules_to_typst() {
+        let chart = "{start_of_verse}\nLorem\n{end_of_verse}\nIpsum\n{start_of_chorus}\nDolor"
+            .parse::<Chart>()
+            .unwrap();
+        let options = ChartDisplayOptions {
+            section_rules: true,
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        chart.print_to_typst_with(&mut output, options).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with(concat!(
+            "#block(stroke: (left: 0.5pt), inset: (left: 0.5em))[\n*Verse*\\\nLorem\\\n]\n",
+            "Ipsum\\\n",
+            "#block(stroke: (left: 0.5pt), inset: (left: 0.5em))[\n*Chorus*\\\nDolor\\\n]\n",
+        )));
+    }
+
+    #[test]
+    fn test_print_chord_diagrams_to_typst() {
+        let chart = CUSTOM_CHORD.parse::<Chart>().unwrap();
+        let options = ChartDisplayOptions {
+            show_diagrams: true,
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        chart.print_to_typst_with(&mut output, options).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), CUSTOM_CHORD_TYPST);
+    }
+
+    #[test]
+    fn test_print_defined_fingers_to_typst() {
+        let chart = "{define: C frets x 3 2 0 1 0 fingers 0 3 2 0 1 0}\n{define: Dsus4 frets x 0 0 2 3 3}\n[C]Lorem [Dsus4]ipsum [G]dolor [F#m]sit"
+            .parse::<Chart>()
+            .unwrap();
+        let options = ChartDisplayOptions {
+            show_diagrams: true,
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        chart.print_to_typst_with(&mut output, options).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains(
+            r#"#chart-chord(tabs: "x32o1o", fingers: "n32n1n")[#"C"] #chart-chord(tabs: "xoo233", fingers: "nnnnnn")[#"Dsus4"] #chart-chord(tabs: "32ooo3", fingers: "nnnnnn")[#"G"] #chart-chord(tabs: "244222", fingers: "nnnnnn")[#"F#m"]"#
+        ));
+    }
+
+    #[test]
+    fn test_print_sharp_chord_diagram_to_typst() {
+        let chart = "{define: F#m frets 2 4 4 2 2 2}\n[F#m]Lorem"
+            .parse::<Chart>()
+            .unwrap();
+        let options = ChartDisplayOptions {
+            show_diagrams: true,
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        chart.print_to_typst_with(&mut output, options).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains(r##"#chart-chord(tabs: "244222", fingers: "nnnnnn")[#"F#m"]"##));
+    }
+
+    #[test]
+    fn test_print_without_diagrams_to_typst() {
+        let chart = CUSTOM_CHORD.parse::<Chart>().unwrap();
+
+        let mut output = Vec::new();
+        chart.print_to_typst(&mut output).unwrap();
+
+        assert!(!String::from_utf8(output).unwrap().contains("chart-chord"));
+    }
 }
@@ -0,0 +1,310 @@
+use std::{fmt::Write, iter};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::chordpro::{
+    charts::{Chart, Line},
+    directives::Directive,
+};
+
+impl Chart {
+    /// Writes the chart as plain text with chords above the lyrics, wrapping
+    /// lines to fit in `width` columns. Lines are broken between words, and
+    /// each chord moves down with the lyrics it is over.
+    pub fn to_plain_text(&self, width: usize) -> String {
+        let mut text = String::new();
+        for header in [self.title(), self.subtitle(), self.artist(), self.comment()]
+            .into_iter()
+            .flatten()
+        {
+            writeln!(text, "{header}").unwrap();
+        }
+
+        for line in &self.lines {
+            let chunks = match line {
+                Line::Content { chunks, .. } => chunks,
+                Line::Verbatim(verbatim) => {
+                    writeln!(text, "{}", verbatim.trim_end_matches('\r')).unwrap();
+                    continue;
+                }
+                Line::Directive(_) => continue,
+            };
+
+            let chords_above = Line::Content {
+                chunks: chunks.clone(),
+                inline: false,
+            }
+            .to_string();
+            let (chord_row, lyric_row) =
+                chords_above.split_once('\n').unwrap_or(("", &chords_above));
+            for (chords, lyrics) in wrap(chord_row, lyric_row, width) {
+                if !chords.is_empty() {
+                    writeln!(text, "{chords}").unwrap();
+                }
+                writeln!(text, "{lyrics}").unwrap();
+            }
+        }
+        text
+    }
+
+    /// The words of the chart without any chords, one line of lyrics per
+    /// line. Sections and comments are written as headings on lines of their
+    /// own, and other directives are left out.
+    pub fn lyrics_only(&self) -> String {
+        let mut text = String::new();
+        for line in &self.lines {
+            match line {
+                Line::Content { chunks, .. } => {
+                    let lyrics = chunks
+                        .iter()
+                        .map(|chunk| chunk.lyrics.as_str())
+                        .collect::<String>();
+                    writeln!(text, "{}", lyrics.trim_end()).unwrap();
+                }
+                Line::Directive(directive) => {
+                    if let Some(heading) = heading(directive) {
+                        writeln!(text, "{heading}").unwrap();
+                    }
+                }
+                Line::Verbatim(_) => {}
+            }
+        }
+        text
+    }
+
+    /// The chords of the chart without any lyrics, with the chords of each
+    /// line separated by spaces. Lines without chords are left blank, and
+    /// sections and comments are written as in [`Chart::lyrics_only`].
+    pub fn chords_only(&self) -> String {
+        let mut text = String::new();
+        for line in &self.lines {
+            match line {
+                Line::Content { chunks, .. } => {
+                    let chords = chunks
+                        .iter()
+                        .filter_map(|chunk| chunk.chord.as_ref())
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>();
+                    writeln!(text, "{}", chords.join(" ")).unwrap();
+                }
+                Line::Directive(directive) => {
+                    if let Some(heading) = heading(directive) {
+                        writeln!(text, "{heading}").unwrap();
+                    }
+                }
+                Line::Verbatim(_) => {}
+            }
+        }
+        text
+    }
+}
+
+/// The heading for a section or comment directive.
+fn heading(directive: &Directive) -> Option<String> {
+    match directive {
+        Directive::SectionStart {
+            label: Some(label), ..
+        } => Some(label.clone()),
+        Directive::SectionStart { kind, label: None } => Some(capitalize(&kind.to_string())),
+        Directive::ChorusRecall => Some("Chorus".to_owned()),
+        Directive::Comment { text, .. } => Some(text.clone()),
+        _ => None,
+    }
+}
+
+pub(crate) fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Splits a row of chords and the row of lyrics below it into rows at most
+/// `width` columns wide. Each break is at a space in the lyrics that is not
+/// under a chord, or at `width` if a word is too long to fit. Columns are
+/// counted by display width, so wide characters take up two.
+fn wrap(chord_row: &str, lyric_row: &str, width: usize) -> Vec<(String, String)> {
+    let width = width.max(1);
+    let chords = columns(chord_row);
+    let lyrics = columns(lyric_row);
+    let len = chords.len().max(lyrics.len());
+    let is_blank = |i: usize| {
+        chords.get(i).is_none_or(|&c| c == " ") && lyrics.get(i).is_none_or(|&c| c == " ")
+    };
+    // Whether a row can be split before column `i` without cutting a wide
+    // character in half.
+    let is_boundary = |i: usize| {
+        chords.get(i).is_none_or(|c| !c.is_empty()) && lyrics.get(i).is_none_or(|c| !c.is_empty())
+    };
+    let slice = |row: &[&str], start: usize, end: usize| {
+        row[start.min(row.len())..end.min(row.len())]
+            .concat()
+            .trim_end()
+            .to_owned()
+    };
+
+    let mut rows = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = if len - start <= width {
+            len
+        } else {
+            (start + 1..=start + width)
+                .rev()
+                .find(|&i| is_blank(i))
+                .or_else(|| (start + 1..=start + width).rev().find(|&i| is_boundary(i)))
+                .or_else(|| (start + width..len).find(|&i| is_boundary(i)))
+                .unwrap_or(len)
+        };
+        rows.push((slice(&chords, start, end), slice(&lyrics, start, end)));
+
+        start = end;
+        while start < len && is_blank(start) {
+            start += 1;
+        }
+        if start >= len {
+            return rows;
+        }
+    }
+}
+
+/// The text in each column of `row`. A wide character is followed by an
+/// empty column for the rest of its width, and characters with no width are
+/// kept with the one before them.
+fn columns(row: &str) -> Vec<&str> {
+    let mut columns = Vec::new();
+    // The column and byte offset of the last character with a width.
+    let mut last = None;
+    for (i, c) in row.char_indices() {
+        let end = i + c.len_utf8();
+        match (row[i..end].width(), last) {
+            (0, Some((column, start))) => columns[column] = &row[start..end],
+            (width, _) => {
+                last = Some((columns.len(), i));
+                columns.push(&row[i..end]);
+                columns.extend(iter::repeat_n("", width.saturating_sub(1)));
+            }
+        }
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chordpro::{charts::Chart, parser::ChartParseOptions};
+
+    const HOW_GREAT_THOU_ART: &str =
+        include_str!("../examples/How-Great-Thou-Art-(Whakaaria-Mai).chordpro");
+    const O_HOLY_NIGHT: &str = include_str!("../examples/O-Holy-Night-.chordpro");
+
+    #[test]
+    fn test_to_plain_text() {
+        let chart = "{title:Lorem}\n[G]Lorem ipsum dolor [C]sit amet, consectetur [D]adipiscing elit\n\nSed do"
+            .parse::<Chart>()
+            .unwrap();
+
+        assert_eq!(
+            chart.to_plain_text(20),
+            "Lorem\nG\nLorem ipsum dolor\nC\nsit amet,\nconsectetur\nD\nadipiscing elit\n\nSed do\n"
+        );
+    }
+
+    #[test]
+    fn test_to_plain_text_chords_over_break() {
+        let chart = "[Am]Lorem [Cmaj7]ipsum [G/B]dolor"
+            .parse::<Chart>()
+            .unwrap();
+
+        assert_eq!(
+            chart.to_plain_text(12),
+            "Am    Cmaj7\nLorem ipsum\nG/B\ndolor\n"
+        );
+        assert_eq!(
+            chart.to_plain_text(40),
+            "Am    Cmaj7 G/B\nLorem ipsum dolor\n"
+        );
+    }
+
+    #[test]
+    fn test_to_plain_text_long_word() {
+        let chart = "[C]Loremipsumdolor".parse::<Chart>().unwrap();
+
+        assert_eq!(chart.to_plain_text(6), "C\nLoremi\npsumdo\nlor\n");
+    }
+
+    #[test]
+    fn test_to_plain_text_wide_characters() {
+        let chart = "[C]你好世界 [G]再见".parse::<Chart>().unwrap();
+
+        assert_eq!(chart.to_plain_text(10), "C\n你好世界\nG\n再见\n");
+        assert_eq!(chart.to_plain_text(14), "C        G\n你好世界 再见\n");
+        assert_eq!(chart.to_plain_text(5), "C\n你好\n世界\nG\n再见\n");
+    }
+
+    #[test]
+    fn test_lyrics_only() {
+        let chart = HOW_GREAT_THOU_ART.parse::<Chart>().unwrap();
+        let lyrics = chart.lyrics_only();
+
+        assert!(!lyrics.contains('['));
+        let mut expected = "Arrangement: Female Key (Db)  Male Key (Bb)  -  76bpm\n".to_owned();
+        for line in HOW_GREAT_THOU_ART
+            .lines()
+            .filter(|line| !line.starts_with('{'))
+        {
+            let mut line = line;
+            let mut stripped = String::new();
+            while let Some((before, rest)) = line.split_once('[') {
+                stripped.push_str(before);
+                line = rest.split_once(']').unwrap().1;
+            }
+            stripped.push_str(line);
+            expected.push_str(stripped.trim_end());
+            expected.push('\n');
+        }
+        assert_eq!(lyrics, expected);
+    }
+
+    #[test]
+    fn test_lyrics_only_chords_above() {
+        let inline = "{soc}\n[G]Lorem ipsum [C]dolor[D]\n\n{ci:Slowly}\nSit amet"
+            .parse::<Chart>()
+            .unwrap();
+        let above = Chart::parse_with(
+            "{soc}\nG           C    D\nLorem ipsum dolor\n\n{ci:Slowly}\nSit amet",
+            &ChartParseOptions::with_extensions(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            inline.lyrics_only(),
+            "Chorus\nLorem ipsum dolor\n\nSlowly\nSit amet\n"
+        );
+        assert_eq!(above.lyrics_only(), inline.lyrics_only());
+    }
+
+    #[test]
+    fn test_chords_only() {
+        let mut chart =
+            Chart::parse_with(O_HOLY_NIGHT, &ChartParseOptions::with_extensions()).unwrap();
+        let chords = chart.chords_only();
+        let lines = chords.lines().collect::<Vec<_>>();
+
+        assert!(lines.contains(&"G D C Em"));
+        assert!(lines.contains(&"G D G"));
+        assert!(!chords.contains("holy"));
+
+        chart.to_numbers().unwrap();
+        assert!(chart.chords_only().lines().any(|line| line == "1 5 4 6m"));
+    }
+
+    #[test]
+    fn test_chords_only_blank_lines() {
+        let chart = "{sov: Verse 1}\n[G]Lorem [D]ipsum\nDolor\n[Em]sit [C]amet"
+            .parse::<Chart>()
+            .unwrap();
+
+        assert_eq!(chart.chords_only(), "Verse 1\nG D\n\nEm C\n");
+    }
+}
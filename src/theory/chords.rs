@@ -1,23 +1,56 @@
 use std::fmt;
 
-use crate::theory::notes::{LetterNote, Note};
+use crate::theory::{
+    notes::{Accidental, LetterNote, MidiPitch, Note, NoteNaming, Spelling},
+    qualities::{StructuredQuality, Triad},
+    scales::Scale,
+};
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Chord {
     pub root: Note,
     pub quality: ChordQuality,
     pub bass: Option<Note>,
+    /// A rhythm marking written straight after the chord, as in Nashville
+    /// charts, such as `◊` to let the chord ring or `.` for a short one.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub annotation: Option<String>,
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChordQuality(pub String);
 
+/// A [`Chord`] paired with a [`NoteNaming`] for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NamedChord<'a>(pub &'a Chord, pub NoteNaming);
+
+/// The symbols to write chord qualities with, for [`Chord::display_with`].
+/// Symbols left as `None` use the canonical `m`, `maj`, `dim` and `aug`, but
+/// the default style leaves chords exactly as they were written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChordStyle {
+    /// e.g. `m`, `-` or `min`.
+    pub minor: Option<String>,
+    /// e.g. `maj`, `M` or `Δ`.
+    pub major_seventh: Option<String>,
+    /// e.g. `dim` or `°`.
+    pub diminished: Option<String>,
+    /// e.g. `aug` or `+`.
+    pub augmented: Option<String>,
+}
+
 impl Chord {
     pub fn major(root: impl Into<Note>) -> Chord {
         Chord {
             root: root.into(),
             quality: ChordQuality::default(),
             bass: None,
+            annotation: None,
         }
     }
 
@@ -26,6 +59,7 @@ impl Chord {
             root: root.into(),
             quality: ChordQuality("m".to_string()),
             bass: None,
+            annotation: None,
         }
     }
 
@@ -35,6 +69,227 @@ impl Chord {
             ..self
         }
     }
+
+    /// This chord reduced to its triad, keeping any suspension but dropping
+    /// extensions, added notes, alterations and the bass note, so `Cm7b5/G`
+    /// becomes `Cm` and `Dsus4add9` becomes `Dsus4`. Qualities that are not
+    /// understood become a major triad.
+    pub fn simplified(&self) -> Chord {
+        let Ok(quality) = self.quality.parse() else {
+            return Chord::major(self.root);
+        };
+        let triad = StructuredQuality {
+            triad: quality.triad,
+            extensions: Vec::new(),
+            sus: quality.sus,
+            adds: Vec::new(),
+            alterations: Vec::new(),
+            altered: false,
+        };
+        Chord {
+            quality: ChordQuality(triad.to_string()),
+            ..Chord::major(self.root)
+        }
+    }
+
+    /// The pitches in this chord, from lowest to highest, with numbered notes
+    /// resolved in `key`. The bass note, if any, is placed below the root.
+    pub fn pitches(&self, key: Scale) -> Vec<MidiPitch> {
+        let root = self.root.as_midi_in_key(key);
+        let mut pitches = self
+            .quality
+            .intervals()
+            .into_iter()
+            .map(|interval| root + interval as i8)
+            .collect::<Vec<_>>();
+        if let Some(bass) = self.bass {
+            let mut bass = bass.as_midi_in_key(key);
+            while bass >= root {
+                bass = bass + -12;
+            }
+            pitches.insert(0, bass);
+        }
+        pitches
+    }
+
+    /// The notes of this chord, spelled from the root by their degree in the
+    /// chord, so C7 has a Bb and Cdim7 a Bbb. Numbered roots and bass notes
+    /// are resolved in `key`. The bass note, if any, comes first.
+    pub fn notes(&self, key: Scale) -> Vec<LetterNote> {
+        let letter = |note: Note| match note {
+            Note::Letter(note) => note,
+            Note::Number(degree) => degree.in_key(key),
+        };
+        let root = letter(self.root);
+        let tones = self
+            .quality
+            .parse()
+            .map_or_else(|_| vec![(1, 0)], |quality| quality.tones());
+        let mut notes = tones
+            .into_iter()
+            .map(|(degree, semitones)| {
+                LetterNote(root.letter() + (degree as i8 - 1), Accidental::NATURAL)
+                    .add_accidentals_to_match(root.as_midi() + semitones as i8)
+            })
+            .collect::<Vec<_>>();
+        if let Some(bass) = self.bass {
+            notes.insert(0, letter(bass));
+        }
+        notes
+    }
+
+    /// Displays this chord with the given naming for its root and bass. The
+    /// plain `Display` impl uses English names.
+    pub fn named(&self, naming: NoteNaming) -> NamedChord<'_> {
+        NamedChord(self, naming)
+    }
+
+    /// Writes this chord with the symbols from `style`. Qualities that are not
+    /// understood are written as they are.
+    pub fn display_with(&self, style: &ChordStyle) -> String {
+        let quality = match self.quality.parse() {
+            Ok(quality) if *style != ChordStyle::default() => quality,
+            _ => return self.to_string(),
+        };
+        let mut s = self.root.to_string();
+        quality
+            .write_styled(&mut s, style)
+            .expect("writing to a String cannot fail");
+        if let Some(bass) = &self.bass {
+            s.push('/');
+            s.push_str(&bass.to_string());
+        }
+        if let Some(annotation) = &self.annotation {
+            s.push_str(annotation);
+        }
+        s
+    }
+
+    /// Transposes this chord by a number of semitones. With
+    /// [`Spelling::FollowKey`], the root is spelled with flats where there is
+    /// a choice and the bass keeps its interval from the root, so `C#m7/G#`
+    /// down a semitone is `Cm7/G`. Chords written as numbers are unchanged.
+    ///
+    /// [`Chart::transpose_by`](crate::chordpro::charts::Chart::transpose_by)
+    /// also respells each chord with [`Chord::spelled_in`] the new key.
+    pub fn transpose(&self, semitones: i8, spelling: Spelling) -> Chord {
+        let Note::Letter(root) = self.root else {
+            return self.clone();
+        };
+        let new_root = (root.as_midi() + semitones).as_letter();
+        // Each note keeps its distance in letters from the root. A bass with a
+        // double accidental can then need a triple one, in which case it is
+        // spelled as simply as possible instead.
+        let transpose = |note: Note| match note {
+            Note::Letter(note) => {
+                let letters = note.letter().as_int() as i8 - root.letter().as_int() as i8;
+                let note = (new_root.letter() + letters)
+                    .natural()
+                    .add_accidentals_to_match(note.as_midi() + semitones);
+                let spelling = match (spelling, note.accidental().as_int()) {
+                    (Spelling::FollowKey, 3..) => Spelling::Sharps,
+                    (Spelling::FollowKey, ..-2) => Spelling::Flats,
+                    (spelling, _) => spelling,
+                };
+                note.as_midi().spelled(spelling).unwrap_or(note).into()
+            }
+            Note::Number(_) => note,
+        };
+        Chord {
+            root: transpose(self.root),
+            quality: self.quality.clone(),
+            bass: self.bass.map(transpose),
+            annotation: self.annotation.clone(),
+        }
+    }
+
+    /// Respells the root and bass as `key` spells them, where they sound the
+    /// same as a note of the scale, so `Gbm` in A is `F#m`. Other notes and
+    /// chords written as numbers are unchanged.
+    pub fn spelled_in(&self, key: Scale) -> Chord {
+        let scale = key.notes();
+        let respell = |note: Note| match note {
+            Note::Letter(note) => scale
+                .into_iter()
+                .find(|n| n.enharmonic_eq(note))
+                .unwrap_or(note)
+                .into(),
+            Note::Number(_) => note,
+        };
+        Chord {
+            root: respell(self.root),
+            quality: self.quality.clone(),
+            bass: self.bass.map(respell),
+            annotation: self.annotation.clone(),
+        }
+    }
+
+    /// Whether the two chords sound the same, comparing the root and bass by
+    /// pitch class rather than spelling, with numbered notes resolved in
+    /// `key`. Qualities that are understood are compared by their parts, so
+    /// `Cmaj7` and `CM7` are the same, and others must be written the same.
+    pub fn enharmonic_eq(&self, other: &Chord, key: Scale) -> bool {
+        let same_pitch = |a: Note, b: Note| {
+            (a.as_midi_in_key(key).as_int() - b.as_midi_in_key(key).as_int()).rem_euclid(12) == 0
+        };
+        let same_bass = match (self.bass, other.bass) {
+            (Some(a), Some(b)) => same_pitch(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        let same_quality = match (self.quality.parse(), other.quality.parse()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => self.quality == other.quality,
+        };
+        same_quality && same_pitch(self.root, other.root) && same_bass
+    }
+
+    /// Whether the two chords have the same root and quality, ignoring any
+    /// bass note, so that C and C/E are the same chord. Roots are compared as
+    /// written, so C# and Db are not the same.
+    pub fn same_triad(&self, other: &Chord) -> bool {
+        self.root == other.root && self.quality == other.quality
+    }
+
+    /// This chord without its bass note, as in root position.
+    pub fn without_bass(&self) -> Chord {
+        Chord {
+            bass: None,
+            ..self.clone()
+        }
+    }
+
+    /// Which inversion this chord is in: 0 for root position, 1 with the third
+    /// in the bass, 2 with the fifth, and so on through the chord tones.
+    /// Returns `None` if the bass note is not in the chord.
+    pub fn inversion(&self, key: Scale) -> Option<u8> {
+        let Some(bass) = self.bass else {
+            return Some(0);
+        };
+        let root = self.root.as_midi_in_key(key);
+        let interval = (bass.as_midi_in_key(key).as_int() - root.as_int()).rem_euclid(12) as u8;
+        self.quality
+            .intervals()
+            .iter()
+            .position(|&tone| tone % 12 == interval)
+            .map(|position| position as u8)
+    }
+}
+
+impl ChordQuality {
+    /// The semitones above the root of each note in a chord of this quality.
+    /// Qualities that are not understood give just the root.
+    pub fn intervals(&self) -> Vec<u8> {
+        self.parse().map_or_else(|_| vec![0], |q| q.intervals())
+    }
+
+    pub fn is_minor(&self) -> bool {
+        self.parse().is_ok_and(|q| q.triad == Triad::Minor)
+    }
+
+    pub fn is_power_chord(&self) -> bool {
+        self.parse().is_ok_and(|q| q.triad == Triad::Power)
+    }
 }
 
 impl LetterNote {
@@ -55,9 +310,23 @@ impl fmt::Debug for Chord {
 
 impl fmt::Display for Chord {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}{}", self.root, self.quality)?;
-        if let Some(bass) = &self.bass {
-            write!(f, "/{bass}")?;
+        write!(f, "{}", self.named(NoteNaming::English))
+    }
+}
+
+impl fmt::Display for NamedChord<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let NamedChord(chord, naming) = *self;
+        let note = |note: Note| match note {
+            Note::Letter(note) => note.named(naming).to_string(),
+            Note::Number(_) => note.to_string(),
+        };
+        write!(f, "{}{}", note(chord.root), chord.quality)?;
+        if let Some(bass) = chord.bass {
+            write!(f, "/{}", note(bass))?;
+        }
+        if let Some(annotation) = &chord.annotation {
+            write!(f, "{annotation}")?;
         }
         Ok(())
     }
@@ -68,3 +337,256 @@ impl fmt::Display for ChordQuality {
         write!(f, "{}", self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::theory::{
+        chords::{Chord, ChordQuality, ChordStyle},
+        notes::{Letter::*, Spelling},
+        scales::Scale,
+    };
+
+    #[test]
+    fn test_transpose() {
+        let transpose = |chord: &str, semitones, spelling| {
+            chord
+                .parse::<Chord>()
+                .unwrap()
+                .transpose(semitones, spelling)
+                .to_string()
+        };
+
+        assert_eq!(transpose("Bb", 2, Spelling::FollowKey), "C");
+        assert_eq!(transpose("C#m7/G#", -1, Spelling::FollowKey), "Cm7/G");
+        assert_eq!(transpose("C", 1, Spelling::FollowKey), "Db");
+        assert_eq!(transpose("C", 1, Spelling::Sharps), "C#");
+        assert_eq!(transpose("D/F#", 1, Spelling::FollowKey), "Eb/G");
+        assert_eq!(transpose("E/G#", -13, Spelling::Flats), "Eb/G");
+        assert_eq!(transpose("4/6", 3, Spelling::FollowKey), "4/6");
+        assert_eq!(transpose("Gb/C##", 1, Spelling::FollowKey), "G/D#");
+        assert_eq!(transpose("Fb/B#", 1, Spelling::FollowKey), "F/B##");
+        assert_eq!(transpose("Fb/B#", 1, Spelling::Sharps), "F/C#");
+        assert_eq!(transpose("C#/Ebb", -1, Spelling::FollowKey), "C/Db");
+    }
+
+    #[test]
+    fn test_spelled_in() {
+        let spelled_in = |chord: &str, key: &str| {
+            chord
+                .parse::<Chord>()
+                .unwrap()
+                .spelled_in(key.parse().unwrap())
+                .to_string()
+        };
+
+        assert_eq!(spelled_in("Gbm", "A"), "F#m");
+        assert_eq!(spelled_in("B/D#", "Gb"), "Cb/Eb");
+        assert_eq!(spelled_in("Bb7", "D"), "Bb7");
+        assert_eq!(spelled_in("6m", "E"), "6m");
+    }
+
+    #[test]
+    fn test_enharmonic_eq() {
+        let key = Scale(E.flat());
+        let eq = |a: &str, b: &str| {
+            a.parse::<Chord>()
+                .unwrap()
+                .enharmonic_eq(&b.parse().unwrap(), key)
+        };
+
+        assert!(eq("C#m7", "Dbm7"));
+        assert!(eq("Fbb", "Eb"));
+        assert!(eq("Eb", "1"));
+        assert!(eq("Ab/C", "4/Dbb"));
+        assert!(eq("C##/E##", "D/F#"));
+        assert!(eq("Cmaj7", "CM7"));
+        assert!(eq("Gø", "Gm7b5"));
+        assert!(!eq("Eb", "Ebm"));
+        assert!(!eq("Ab/C", "Ab"));
+        assert!(!eq("Ab/C", "G#/B"));
+    }
+
+    #[test]
+    fn test_same_triad() {
+        let same = |a: &str, b: &str| a.parse::<Chord>().unwrap().same_triad(&b.parse().unwrap());
+
+        assert!(same("C", "C/E"));
+        assert!(same("Am7/G", "Am7"));
+        assert!(same("1/3", "1/5"));
+        assert!(same("b7/2", "b7"));
+        assert!(!same("C/E", "Cm/E"));
+        assert!(!same("C", "1"));
+        assert!(!same("4/6", "5/6"));
+
+        let chord = "F#m7/C#".parse::<Chord>().unwrap();
+        assert_eq!(chord.without_bass().to_string(), "F#m7");
+        assert!(chord.without_bass().same_triad(&chord));
+        assert_eq!(
+            "6m/1".parse::<Chord>().unwrap().without_bass(),
+            Chord::minor(6)
+        );
+    }
+
+    #[test]
+    fn test_hash_set() {
+        let chords = ["G", "C", "G", "D/F#", "Em", "D/F#", "G", "Gm"]
+            .map(|chord| chord.parse::<Chord>().unwrap())
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        assert_eq!(chords.len(), 5);
+        assert!(chords.contains(&Chord::major(D.natural()).over(F.sharp())));
+        assert!(chords.contains(&Chord::minor(G.natural())));
+    }
+
+    #[test]
+    fn test_power_chord() {
+        let c5 = "C5".parse::<Chord>().unwrap();
+        assert!(c5.quality.is_power_chord());
+        assert_eq!(c5.to_string(), "C5");
+        assert_eq!(c5.quality.intervals(), vec![0, 7]);
+        assert_eq!(
+            c5.pitches(Scale(C.natural())),
+            vec![C.natural().as_midi(), G.natural().as_midi()]
+        );
+
+        let c5_over_g = "C5/G".parse::<Chord>().unwrap();
+        assert_eq!(c5_over_g.to_string(), "C5/G");
+        assert_eq!(
+            c5_over_g
+                .pitches(Scale(G.natural()))
+                .iter()
+                .map(|p| p.as_int())
+                .collect::<Vec<_>>(),
+            vec![55, 60, 67]
+        );
+    }
+
+    #[test]
+    fn test_notes() {
+        let notes = |chord: &str, key: &str| {
+            chord
+                .parse::<Chord>()
+                .unwrap()
+                .notes(key.parse().unwrap())
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        for (chord, expected) in [
+            ("C", "C E G"),
+            ("Cm", "C Eb G"),
+            ("C7", "C E G Bb"),
+            ("Cmaj7", "C E G B"),
+            ("C9", "C E G Bb D"),
+            ("C6", "C E G A"),
+            ("Csus4", "C F G"),
+            ("Csus2", "C D G"),
+            ("Cadd9", "C E G D"),
+            ("Cdim7", "C Eb Gb Bbb"),
+            ("Caug", "C E G#"),
+            ("F#7", "F# A# C# E"),
+            ("Gb7", "Gb Bb Db Fb"),
+            ("D/F#", "F# D F# A"),
+            ("5m7/4", "Bb C Eb G Bb"),
+        ] {
+            assert_eq!(notes(chord, "F"), expected, "{chord:?}");
+        }
+    }
+
+    #[test]
+    fn test_pitches() {
+        let midi = |chord: &str, key: Scale| {
+            chord
+                .parse::<Chord>()
+                .unwrap()
+                .pitches(key)
+                .iter()
+                .map(|p| p.as_int())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(midi("G7", Scale(C.natural())), vec![67, 71, 74, 77]);
+        assert_eq!(midi("5m7", Scale(D.natural())), vec![69, 72, 76, 79]);
+        assert_eq!(midi("Cadd9", Scale(C.natural())), vec![60, 64, 67, 74]);
+        assert_eq!(midi("Dsus4/A", Scale(D.natural())), vec![57, 62, 67, 69]);
+
+        let unknown = Chord {
+            quality: ChordQuality("?".to_string()),
+            ..Chord::major(E.flat())
+        };
+        assert_eq!(
+            unknown.pitches(Scale(C.natural())),
+            vec![E.flat().as_midi()]
+        );
+    }
+
+    #[test]
+    fn test_inversion() {
+        let inversion = |chord: &str| {
+            chord
+                .parse::<Chord>()
+                .unwrap()
+                .inversion(Scale(C.natural()))
+        };
+
+        assert_eq!(inversion("C"), Some(0));
+        assert_eq!(inversion("C/C"), Some(0));
+        assert_eq!(inversion("C/E"), Some(1));
+        assert_eq!(inversion("C/G"), Some(2));
+        assert_eq!(inversion("C7/Bb"), Some(3));
+        assert_eq!(inversion("Am/C"), Some(1));
+        assert_eq!(inversion("1/3"), Some(1));
+        assert_eq!(inversion("C/D"), None);
+    }
+
+    #[test]
+    fn test_simplified() {
+        let simplified = [
+            "Cmaj7",
+            "Cm7b5/G",
+            "C5",
+            "Cdim7",
+            "Caug",
+            "Dsus4add9",
+            "C7alt",
+            "Cmsm",
+        ]
+        .map(|chord| chord.parse::<Chord>().unwrap().simplified().to_string());
+        assert_eq!(
+            simplified,
+            ["C", "Cm", "C5", "Cdim", "Caug", "Dsus4", "C", "C"]
+        );
+    }
+
+    #[test]
+    fn test_display_with() {
+        let chords = ["Cmaj7", "CM7", "Amin7", "Bdim", "Gaug", "Dmin7/C", "Csus4"]
+            .map(|c| c.parse::<Chord>().unwrap());
+
+        let as_written = chords
+            .clone()
+            .map(|c| c.display_with(&ChordStyle::default()));
+        assert_eq!(
+            as_written,
+            ["Cmaj7", "CM7", "Amin7", "Bdim", "Gaug", "Dmin7/C", "Csus4"]
+        );
+
+        let jazz = ChordStyle {
+            minor: Some("-".to_owned()),
+            major_seventh: Some("Δ".to_owned()),
+            diminished: Some("°".to_owned()),
+            augmented: Some("+".to_owned()),
+        };
+        let styled = chords.map(|c| c.display_with(&jazz));
+        assert_eq!(styled, ["CΔ7", "CΔ7", "A-7", "B°", "G+", "D-7/C", "Csus4"]);
+
+        for chord in styled {
+            assert_eq!(chord.parse::<Chord>().unwrap().display_with(&jazz), chord);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::theory::{
+    chords::{Chord, ChordQuality},
+    instruments::Instrument,
+    notes::{Letter::*, LetterNote, Note},
+};
+
+const GUITAR: &[(LetterNote, &str, &str)] = &[
+    (C.natural(), "", "x32010"),
+    (C.natural(), "m", "x35543"),
+    (C.natural(), "7", "x32310"),
+    (D.flat(), "", "x46664"),
+    (D.flat(), "m", "x46654"),
+    (D.natural(), "", "xx0232"),
+    (D.natural(), "m", "xx0231"),
+    (D.natural(), "7", "xx0212"),
+    (E.flat(), "", "x68886"),
+    (E.flat(), "m", "x68876"),
+    (E.natural(), "", "022100"),
+    (E.natural(), "m", "022000"),
+    (E.natural(), "7", "020100"),
+    (F.natural(), "", "133211"),
+    (F.natural(), "m", "133111"),
+    (F.natural(), "7", "131211"),
+    (G.flat(), "", "244322"),
+    (G.flat(), "m", "244222"),
+    (G.natural(), "", "320003"),
+    (G.natural(), "m", "355333"),
+    (G.natural(), "7", "320001"),
+    (A.flat(), "", "466544"),
+    (A.flat(), "m", "466444"),
+    (A.natural(), "", "x02220"),
+    (A.natural(), "m", "x02210"),
+    (A.natural(), "7", "x02020"),
+    (B.flat(), "", "x13331"),
+    (B.flat(), "m", "x13321"),
+    (B.natural(), "", "x24442"),
+    (B.natural(), "m", "x24432"),
+    (B.natural(), "7", "x21202"),
+];
+
+const UKULELE: &[(LetterNote, &str, &str)] = &[
+    (C.natural(), "", "0003"),
+    (C.natural(), "m", "0333"),
+    (C.natural(), "7", "0001"),
+    (D.flat(), "", "1114"),
+    (D.flat(), "m", "1104"),
+    (D.natural(), "", "2220"),
+    (D.natural(), "m", "2210"),
+    (D.natural(), "7", "2223"),
+    (E.flat(), "", "0331"),
+    (E.flat(), "m", "3321"),
+    (E.natural(), "", "1402"),
+    (E.natural(), "m", "0432"),
+    (E.natural(), "7", "1202"),
+    (F.natural(), "", "2010"),
+    (F.natural(), "m", "1013"),
+    (F.natural(), "7", "2313"),
+    (G.flat(), "", "3121"),
+    (G.flat(), "m", "2120"),
+    (G.natural(), "", "0232"),
+    (G.natural(), "m", "0231"),
+    (G.natural(), "7", "0212"),
+    (A.flat(), "", "5343"),
+    (A.flat(), "m", "4342"),
+    (A.natural(), "", "2100"),
+    (A.natural(), "m", "2000"),
+    (A.natural(), "7", "0100"),
+    (B.flat(), "", "3211"),
+    (B.flat(), "m", "3111"),
+    (B.natural(), "", "4322"),
+    (B.natural(), "m", "4222"),
+    (B.natural(), "7", "2322"),
+];
+
+const MANDOLIN: &[(LetterNote, &str, &str)] = &[
+    (C.natural(), "", "0230"),
+    (C.natural(), "m", "0133"),
+    (C.natural(), "7", "3230"),
+    (D.flat(), "", "1341"),
+    (D.flat(), "m", "1240"),
+    (D.natural(), "", "2002"),
+    (D.natural(), "m", "2001"),
+    (D.natural(), "7", "2032"),
+    (E.flat(), "", "0113"),
+    (E.flat(), "m", "3112"),
+    (E.natural(), "", "1220"),
+    (E.natural(), "m", "0220"),
+    (E.natural(), "7", "1020"),
+    (F.natural(), "", "2331"),
+    (F.natural(), "m", "1331"),
+    (F.natural(), "7", "2101"),
+    (G.flat(), "", "3442"),
+    (G.flat(), "m", "2442"),
+    (G.natural(), "", "0023"),
+    (G.natural(), "m", "0013"),
+    (G.natural(), "7", "0021"),
+    (A.flat(), "", "1134"),
+    (A.flat(), "m", "1124"),
+    (A.natural(), "", "2240"),
+    (A.natural(), "m", "2230"),
+    (A.natural(), "7", "2243"),
+    (B.flat(), "", "3011"),
+    (B.flat(), "m", "3341"),
+    (B.natural(), "", "4122"),
+    (B.natural(), "m", "4022"),
+    (B.natural(), "7", "2122"),
+];
+
+const BASS: &[(LetterNote, &str, &str)] = &[
+    (C.natural(), "", "x320"),
+    (C.natural(), "m", "x310"),
+    (C.natural(), "7", "x323"),
+    (D.flat(), "", "x431"),
+    (D.flat(), "m", "x421"),
+    (D.natural(), "", "x542"),
+    (D.natural(), "m", "x532"),
+    (D.natural(), "7", "x545"),
+    (E.flat(), "", "x653"),
+    (E.flat(), "m", "x643"),
+    (E.natural(), "", "0221"),
+    (E.natural(), "m", "0220"),
+    (E.natural(), "7", "0201"),
+    (F.natural(), "", "1332"),
+    (F.natural(), "m", "1331"),
+    (F.natural(), "7", "1012"),
+    (G.flat(), "", "2443"),
+    (G.flat(), "m", "2442"),
+    (G.natural(), "", "3200"),
+    (G.natural(), "m", "3100"),
+    (G.natural(), "7", "3230"),
+    (A.flat(), "", "4311"),
+    (A.flat(), "m", "4211"),
+    (A.natural(), "", "5422"),
+    (A.natural(), "m", "x025"),
+    (A.natural(), "7", "5420"),
+    (B.flat(), "", "6533"),
+    (B.flat(), "m", "6433"),
+    (B.natural(), "", "7644"),
+    (B.natural(), "m", "7544"),
+    (B.natural(), "7", "x212"),
+];
+
+impl Instrument {
+    /// Looks up the built-in fingering for a letter chord, ignoring any bass
+    /// note. Enharmonic roots (e.g. `A#` and `Bb`) share a fingering.
+    pub fn fingering(self, chord: &Chord) -> Option<Vec<Option<u8>>> {
+        let shapes = match self {
+            Instrument::Guitar => GUITAR,
+            Instrument::Ukulele => UKULELE,
+            Instrument::Mandolin => MANDOLIN,
+            Instrument::Bass => BASS,
+        };
+        shapes.iter().find_map(|&(root, quality, frets)| {
+            let shape = Chord {
+                root: root.into(),
+                quality: ChordQuality(quality.to_owned()),
+                bass: None,
+                annotation: None,
+            };
+            is_same_shape(&shape, chord).then(|| {
+                frets
+                    .chars()
+                    .map(|c| c.to_digit(10).map(|d| d as u8))
+                    .collect()
+            })
+        })
+    }
+}
+
+/// Whether two letter chords have the same root pitch class and quality.
+pub fn is_same_shape(a: &Chord, b: &Chord) -> bool {
+    let (Note::Letter(a_root), Note::Letter(b_root)) = (a.root, b.root) else {
+        return false;
+    };
+    a_root.as_midi().as_int().rem_euclid(12) == b_root.as_midi().as_int().rem_euclid(12)
+        && a.quality == b.quality
+}
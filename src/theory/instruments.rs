@@ -0,0 +1,33 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Instrument {
+    #[default]
+    Guitar,
+    Ukulele,
+    Mandolin,
+    Bass,
+}
+
+impl Instrument {
+    pub const fn string_count(self) -> usize {
+        match self {
+            Instrument::Guitar => 6,
+            Instrument::Ukulele => 4,
+            Instrument::Mandolin => 4,
+            Instrument::Bass => 4,
+        }
+    }
+}
+
+impl fmt::Display for Instrument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instrument::Guitar => write!(f, "guitar"),
+            Instrument::Ukulele => write!(f, "ukulele"),
+            Instrument::Mandolin => write!(f, "mandolin"),
+            Instrument::Bass => write!(f, "bass"),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+
+use crate::theory::notes::LetterNote;
+
+/// A simple (within an octave) ascending interval between two spelled notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    number: u8,
+    quality: IntervalQuality,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntervalQuality {
+    /// Diminished, possibly more than once.
+    Diminished(u8),
+    Minor,
+    Perfect,
+    Major,
+    /// Augmented, possibly more than once.
+    Augmented(u8),
+}
+
+/// The semitones in the major or perfect interval of each number.
+const NATURAL_SEMITONES: [i8; 7] = [0, 2, 4, 5, 7, 9, 11];
+
+impl Interval {
+    /// The interval number, from 1 (unison) to 7 (seventh).
+    pub fn number(self) -> u8 {
+        self.number
+    }
+
+    pub fn quality(self) -> IntervalQuality {
+        self.quality
+    }
+
+    pub fn semitones(self) -> i8 {
+        let natural = NATURAL_SEMITONES[self.number as usize - 1];
+        let offset = match (self.quality, is_perfect(self.number)) {
+            (IntervalQuality::Diminished(n), true) => -(n as i8),
+            (IntervalQuality::Diminished(n), false) => -(n as i8) - 1,
+            (IntervalQuality::Minor, _) => -1,
+            (IntervalQuality::Perfect | IntervalQuality::Major, _) => 0,
+            (IntervalQuality::Augmented(n), _) => n as i8,
+        };
+        natural + offset
+    }
+}
+
+impl LetterNote {
+    /// The interval from this note up to `other`. The spelling matters, so
+    /// C to Eb is a minor third while C to D# is an augmented second.
+    pub fn interval_to(self, other: LetterNote) -> Interval {
+        let number = (other.letter().as_int() as i8 - self.letter().as_int() as i8).rem_euclid(7);
+        let semitones = (other.as_midi().as_int() - self.as_midi().as_int()).rem_euclid(12);
+        let mut offset = semitones - NATURAL_SEMITONES[number as usize];
+        if offset > 6 {
+            offset -= 12;
+        } else if offset < -6 {
+            offset += 12;
+        }
+
+        let number = number as u8 + 1;
+        let quality = match (offset, is_perfect(number)) {
+            (0, true) => IntervalQuality::Perfect,
+            (0, false) => IntervalQuality::Major,
+            (-1, false) => IntervalQuality::Minor,
+            (n, true) if n < 0 => IntervalQuality::Diminished(-n as u8),
+            (n, false) if n < 0 => IntervalQuality::Diminished(-n as u8 - 1),
+            (n, _) => IntervalQuality::Augmented(n as u8),
+        };
+        Interval { number, quality }
+    }
+}
+
+/// Whether intervals with this number are perfect rather than major or minor.
+fn is_perfect(number: u8) -> bool {
+    matches!(number, 1 | 4 | 5)
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.quality, self.number)
+    }
+}
+
+impl fmt::Display for IntervalQuality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IntervalQuality::Diminished(n) => write!(f, "{}", "d".repeat(n as usize)),
+            IntervalQuality::Minor => write!(f, "m"),
+            IntervalQuality::Perfect => write!(f, "P"),
+            IntervalQuality::Major => write!(f, "M"),
+            IntervalQuality::Augmented(n) => write!(f, "{}", "A".repeat(n as usize)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::theory::notes::Letter::*;
+
+    #[test]
+    fn test_interval_to() {
+        let interval = C.natural().interval_to(E.flat());
+        assert_eq!(interval.to_string(), "m3");
+        assert_eq!(interval.semitones(), 3);
+
+        let interval = C.natural().interval_to(D.sharp());
+        assert_eq!(interval.to_string(), "A2");
+        assert_eq!(interval.semitones(), 3);
+
+        assert_eq!(C.natural().interval_to(G.natural()).to_string(), "P5");
+        assert_eq!(D.natural().interval_to(C.natural()).to_string(), "m7");
+        assert_eq!(C.natural().interval_to(C.natural()).to_string(), "P1");
+        assert_eq!(C.natural().interval_to(B.sharp()).to_string(), "A7");
+        assert_eq!(E.natural().interval_to(G.flat()).to_string(), "d3");
+        assert_eq!(C.sharp().interval_to(B.flat()).to_string(), "d7");
+        assert_eq!(C.sharp().interval_to(B.flat()).semitones(), 9);
+    }
+
+    #[test]
+    fn test_tritones() {
+        for (from, to, name) in [
+            (C.natural(), F.sharp(), "A4"),
+            (C.natural(), G.flat(), "d5"),
+            (F.natural(), B.natural(), "A4"),
+            (B.natural(), F.natural(), "d5"),
+            (E.flat(), A.natural(), "A4"),
+            (F.sharp(), C.natural(), "d5"),
+        ] {
+            let interval = from.interval_to(to);
+            assert_eq!(interval.to_string(), name);
+            assert_eq!(interval.semitones(), 6);
+        }
+    }
+}
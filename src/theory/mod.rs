@@ -1,3 +1,7 @@
 pub mod chords;
+pub mod fingerings;
+pub mod instruments;
+pub mod intervals;
 pub mod notes;
+pub mod qualities;
 pub mod scales;
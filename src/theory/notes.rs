@@ -2,19 +2,26 @@ use std::{fmt, ops::Add};
 
 use crate::theory::scales::ScaleDegree;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct MidiPitch(u8);
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+/// A note written as a letter or as a number. Letter notes are ordered before
+/// numbers. Letter notes are then ordered by letter from C to B and then by
+/// accidental, so Cb comes before C and B# after B. Numbers are ordered by
+/// degree and then by accidental.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Note {
     Letter(LetterNote),
     Number(ScaleDegree),
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LetterNote(pub Letter, pub Accidental);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Letter {
     C,
     D,
@@ -25,10 +32,75 @@ pub enum Letter {
     B,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Accidental(i8);
 
+/// The convention for naming notes. In German naming, `H` is B natural and `B`
+/// is B flat.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NoteNaming {
+    #[default]
+    English,
+    German,
+}
+
+/// How to spell notes that fall between the natural letters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Spelling {
+    Sharps,
+    Flats,
+    /// Spell notes as they appear in the key, which may need double sharps or
+    /// double flats.
+    #[default]
+    FollowKey,
+}
+
 impl MidiPitch {
+    /// The pitch with the given MIDI note number.
+    ///
+    /// # Panics
+    /// Panics if `pitch` is above 127, the highest MIDI note.
+    pub const fn new(pitch: u8) -> Self {
+        assert!(pitch <= 127, "MIDI pitches go up to 127");
+        MidiPitch(pitch)
+    }
+
+    /// The pitch of `note` in `octave`. Octaves are numbered as in scientific
+    /// pitch notation, so middle C (60) is C4, and they follow the letter, so
+    /// B#4 is the same pitch as C5.
+    ///
+    /// # Panics
+    /// Panics if the pitch is outside the MIDI range of 0 to 127.
+    pub const fn from_note(note: LetterNote, octave: i8) -> Self {
+        let pitch = (octave as i16 + 1) * 12 + note.letter().as_midi().as_int() as i16 - 60
+            + note.accidental().as_int() as i16;
+        assert!(
+            0 <= pitch && pitch <= 127,
+            "pitch is outside the MIDI range"
+        );
+        MidiPitch(pitch as u8)
+    }
+
+    /// The pitch with the given note number, moved by octaves into the MIDI
+    /// range if it falls outside it. This keeps the pitch class, so notes
+    /// transposed past either end of the range are still spelled correctly.
+    const fn wrapping(mut pitch: i16) -> Self {
+        while pitch < 0 {
+            pitch += 12;
+        }
+        while pitch > 127 {
+            pitch -= 12;
+        }
+        MidiPitch(pitch as u8)
+    }
+
+    /// The octave of this pitch in scientific pitch notation, where middle C
+    /// (60) is in octave 4.
+    pub const fn octave(self) -> i8 {
+        (self.0 / 12) as i8 - 1
+    }
+
     pub const fn as_int(self) -> i8 {
         self.0 as i8
     }
@@ -46,6 +118,38 @@ impl MidiPitch {
         };
         LetterNote(letter, Accidental::NATURAL).add_accidentals_to_match(self)
     }
+
+    /// Spells this pitch using only naturals and the accidental preferred by
+    /// `spelling`. Returns `None` for [`Spelling::FollowKey`], which depends on
+    /// the key.
+    pub const fn spelled(self, spelling: Spelling) -> Option<LetterNote> {
+        let sharp = match spelling {
+            Spelling::Sharps => true,
+            Spelling::Flats => false,
+            Spelling::FollowKey => return None,
+        };
+        let (letter, accidental) = match (self.0 % 12, sharp) {
+            (0, _) => (Letter::C, Accidental::NATURAL),
+            (1, true) => (Letter::C, Accidental::SHARP),
+            (1, false) => (Letter::D, Accidental::FLAT),
+            (2, _) => (Letter::D, Accidental::NATURAL),
+            (3, true) => (Letter::D, Accidental::SHARP),
+            (3, false) => (Letter::E, Accidental::FLAT),
+            (4, _) => (Letter::E, Accidental::NATURAL),
+            (5, _) => (Letter::F, Accidental::NATURAL),
+            (6, true) => (Letter::F, Accidental::SHARP),
+            (6, false) => (Letter::G, Accidental::FLAT),
+            (7, _) => (Letter::G, Accidental::NATURAL),
+            (8, true) => (Letter::G, Accidental::SHARP),
+            (8, false) => (Letter::A, Accidental::FLAT),
+            (9, _) => (Letter::A, Accidental::NATURAL),
+            (10, true) => (Letter::A, Accidental::SHARP),
+            (10, false) => (Letter::B, Accidental::FLAT),
+            (11, _) => (Letter::B, Accidental::NATURAL),
+            _ => unreachable!(),
+        };
+        Some(LetterNote(letter, accidental))
+    }
 }
 
 impl LetterNote {
@@ -59,8 +163,19 @@ impl LetterNote {
 
     pub const fn as_midi(self) -> MidiPitch {
         let base_pitch = self.letter().as_midi().as_int();
-        let pitch = base_pitch + self.accidental().as_int();
-        MidiPitch(pitch as u8)
+        MidiPitch::wrapping(base_pitch as i16 + self.accidental().as_int() as i16)
+    }
+
+    /// Displays this note with the given naming. The plain `Display` impl
+    /// uses English names.
+    pub const fn named(self, naming: NoteNaming) -> NamedNote {
+        NamedNote(self, naming)
+    }
+
+    /// Whether the two notes are the same pitch class, however they are
+    /// spelled, so C# equals Db and Fbb equals Eb.
+    pub const fn enharmonic_eq(self, other: LetterNote) -> bool {
+        (self.as_midi().as_int() - other.as_midi().as_int()).rem_euclid(12) == 0
     }
 
     pub const fn add_accidentals_to_match(self, target: MidiPitch) -> LetterNote {
@@ -143,7 +258,7 @@ impl Accidental {
 
     pub fn new(delta: i8) -> Self {
         assert!(
-            -2 <= delta && delta <= 2,
+            (-2..=2).contains(&delta),
             "{delta} is too large to be an accidental"
         );
         Self(delta)
@@ -152,8 +267,32 @@ impl Accidental {
     pub const fn as_int(self) -> i8 {
         self.0
     }
+
+    /// Displays this accidental in the given style. The plain `Display` impl
+    /// is the one used in chord names.
+    pub const fn styled(self, style: AccidentalStyle) -> StyledAccidental {
+        StyledAccidental(self, style)
+    }
+}
+
+/// How to display an accidental outside of a chord name, for example in a key
+/// signature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccidentalStyle {
+    /// Show `♮` for naturals instead of nothing.
+    pub natural: bool,
+    /// Use `♭`, `♯`, `𝄫` and `𝄪` instead of `b` and `#`.
+    pub unicode: bool,
 }
 
+/// An [`Accidental`] paired with an [`AccidentalStyle`] for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StyledAccidental(pub Accidental, pub AccidentalStyle);
+
+/// A [`LetterNote`] paired with a [`NoteNaming`] for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NamedNote(pub LetterNote, pub NoteNaming);
+
 impl From<LetterNote> for Note {
     fn from(note: LetterNote) -> Self {
         Note::Letter(note)
@@ -182,7 +321,7 @@ impl Add<i8> for MidiPitch {
     type Output = MidiPitch;
 
     fn add(self, rhs: i8) -> Self::Output {
-        MidiPitch((self.as_int() + rhs) as u8)
+        MidiPitch::wrapping(self.as_int() as i16 + rhs as i16)
     }
 }
 
@@ -190,7 +329,7 @@ impl Add<Accidental> for MidiPitch {
     type Output = MidiPitch;
 
     fn add(self, rhs: Accidental) -> Self::Output {
-        MidiPitch((self.as_int() + rhs.as_int()) as u8)
+        MidiPitch::wrapping(self.as_int() as i16 + rhs.as_int() as i16)
     }
 }
 
@@ -232,6 +371,17 @@ impl fmt::Display for LetterNote {
     }
 }
 
+impl fmt::Display for NamedNote {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let NamedNote(note, naming) = *self;
+        match (naming, note) {
+            (NoteNaming::German, LetterNote(Letter::B, Accidental::FLAT)) => write!(f, "B"),
+            (NoteNaming::German, LetterNote(Letter::B, accidental)) => write!(f, "H{accidental}"),
+            _ => write!(f, "{note}"),
+        }
+    }
+}
+
 impl fmt::Display for Letter {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{self:?}")
@@ -253,9 +403,27 @@ impl fmt::Display for Accidental {
     }
 }
 
+impl fmt::Display for StyledAccidental {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let StyledAccidental(accidental, style) = *self;
+        match accidental {
+            Accidental::NATURAL if style.natural => write!(f, "♮"),
+            _ if !style.unicode => write!(f, "{accidental}"),
+            Accidental::DOUBLE_FLAT => write!(f, "𝄫"),
+            Accidental::FLAT => write!(f, "♭"),
+            Accidental::SHARP => write!(f, "♯"),
+            Accidental::DOUBLE_SHARP => write!(f, "𝄪"),
+            _ => Ok(()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::theory::notes::{Accidental, Letter, LetterNote, MidiPitch};
+    use crate::theory::{
+        notes::{Accidental, AccidentalStyle, Letter, LetterNote, MidiPitch, Note, NoteNaming},
+        scales::ScaleDegree,
+    };
 
     use Letter::*;
 
@@ -275,4 +443,129 @@ mod test {
         assert_eq!(LetterNote(E, NATURAL).as_midi(), MidiPitch(64));
         assert_eq!(LetterNote(B, FLAT).as_midi(), MidiPitch(70));
     }
+
+    #[test]
+    fn test_letter_note_enharmonic_eq() {
+        assert!(C.sharp().enharmonic_eq(D.flat()));
+        assert!(LetterNote(F, Accidental::DOUBLE_FLAT).enharmonic_eq(E.flat()));
+        assert!(LetterNote(G, Accidental::DOUBLE_SHARP).enharmonic_eq(A.natural()));
+        assert!(B.sharp().enharmonic_eq(C.natural()));
+        assert!(C.flat().enharmonic_eq(B.natural()));
+        assert!(!C.sharp().enharmonic_eq(D.natural()));
+    }
+
+    #[test]
+    fn test_midi_pitch_octaves() {
+        assert_eq!(MidiPitch::from_note(C.natural(), 4), MidiPitch::new(60));
+        assert_eq!(MidiPitch::from_note(A.natural(), 4), MidiPitch::new(69));
+        assert_eq!(MidiPitch::from_note(C.natural(), -1), MidiPitch::new(0));
+        assert_eq!(MidiPitch::from_note(G.natural(), 9), MidiPitch::new(127));
+        assert_eq!(MidiPitch::from_note(B.sharp(), 4), MidiPitch::new(72));
+        assert_eq!(MidiPitch::from_note(C.flat(), 4), MidiPitch::new(59));
+        assert_eq!(MidiPitch::new(59).octave(), 3);
+        assert_eq!(MidiPitch::new(60).octave(), 4);
+        assert_eq!(LetterNote(C, NATURAL).as_midi().octave(), 4);
+
+        for octave in -1..=8 {
+            for letter in [C, D, E, F, G, A, B] {
+                let pitch = MidiPitch::from_note(letter.natural(), octave);
+                assert_eq!(pitch.octave(), octave);
+                assert_eq!(pitch.as_letter(), letter.natural());
+            }
+        }
+    }
+
+    #[test]
+    fn test_midi_pitch_add_across_octaves() {
+        let b3 = MidiPitch::from_note(B.natural(), 3);
+        assert_eq!(b3 + 1, MidiPitch::from_note(C.natural(), 4));
+        assert_eq!((b3 + 1).octave(), 4);
+        assert_eq!((b3 + -12).octave(), 2);
+    }
+
+    #[test]
+    fn test_midi_pitch_add_past_range() {
+        let low = MidiPitch::new(0);
+        assert_eq!(low + -2, MidiPitch::new(10));
+        assert_eq!((low + -2).as_letter(), B.flat());
+        assert_eq!(low + -127, MidiPitch::new(5));
+
+        let high = MidiPitch::new(127);
+        assert_eq!((high + 10).as_letter(), F.natural());
+        assert_eq!(high + 127, MidiPitch::new(122));
+        assert_eq!(high + Accidental::DOUBLE_SHARP, MidiPitch::new(117));
+
+        for semitones in [-128, -100, -64, -13, 13, 64, 100, 127] {
+            for pitch in [0, 1, 60, 126, 127] {
+                let result = MidiPitch::new(pitch) + semitones;
+                assert!(result.as_int() >= 0);
+                assert_eq!(
+                    result.as_int().rem_euclid(12),
+                    (pitch as i16 + semitones as i16).rem_euclid(12) as i8
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_midi_pitch_out_of_range() {
+        MidiPitch::from_note(A.natural(), 9);
+    }
+
+    #[test]
+    fn test_note_order() {
+        let mut notes: Vec<Note> = vec![
+            ScaleDegree::new(1, NATURAL).into(),
+            B.sharp().into(),
+            ScaleDegree::new(7, FLAT).into(),
+            C.natural().into(),
+            C.flat().into(),
+            D.flat().into(),
+            C.sharp().into(),
+        ];
+        notes.sort();
+
+        assert_eq!(
+            notes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["Cb", "C", "C#", "Db", "B#", "1", "b7"]
+        );
+    }
+
+    #[test]
+    fn test_display_german() {
+        let names = [B.natural(), B.flat(), B.sharp(), B.double_flat(), A.sharp()]
+            .map(|n| n.named(NoteNaming::German).to_string());
+
+        assert_eq!(names, ["H", "B", "H#", "Hbb", "A#"]);
+    }
+
+    #[test]
+    fn test_display_styled_accidental() {
+        let accidentals = [
+            Accidental::DOUBLE_FLAT,
+            FLAT,
+            NATURAL,
+            Accidental::SHARP,
+            Accidental::DOUBLE_SHARP,
+        ];
+        let show = |style| accidentals.map(|a| a.styled(style).to_string());
+
+        assert_eq!(show(AccidentalStyle::default()), ["bb", "b", "", "#", "##"]);
+        assert_eq!(
+            show(AccidentalStyle {
+                natural: true,
+                unicode: false
+            }),
+            ["bb", "b", "♮", "#", "##"]
+        );
+        assert_eq!(
+            show(AccidentalStyle {
+                natural: true,
+                unicode: true
+            }),
+            ["𝄫", "♭", "♮", "♯", "𝄪"]
+        );
+        assert_eq!(NATURAL.to_string(), "");
+    }
 }
@@ -0,0 +1,297 @@
+use std::fmt;
+
+use crate::theory::{
+    chords::{ChordQuality, ChordStyle},
+    notes::Accidental,
+};
+
+/// A chord quality broken down into its parts, e.g. `m7sus4add13`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructuredQuality {
+    pub triad: Triad,
+    /// The extensions in ascending order, e.g. `9` is a seventh and a ninth.
+    pub extensions: Vec<Extension>,
+    pub sus: Option<Sus>,
+    /// The degrees of any added notes.
+    pub adds: Vec<u8>,
+    /// Raised or lowered notes, e.g. `b5` and `#9`.
+    pub alterations: Vec<Alteration>,
+    /// Whether the chord is marked `alt`, leaving the choice of altered fifth
+    /// and ninth to the player.
+    pub altered: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Triad {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    /// A root and fifth with no third, e.g. `C5`.
+    Power,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Extension {
+    Sixth,
+    Seventh,
+    MajorSeventh,
+    Ninth,
+    Eleventh,
+    Thirteenth,
+}
+
+/// A fifth, ninth, eleventh or thirteenth raised or lowered by an
+/// accidental, e.g. the `b5` of a half-diminished `m7b5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alteration {
+    pub degree: u8,
+    pub accidental: Accidental,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sus {
+    Sus2,
+    Sus4,
+}
+
+impl ChordQuality {
+    pub fn parse(&self) -> Result<StructuredQuality, String> {
+        self.0.parse()
+    }
+}
+
+impl StructuredQuality {
+    /// The semitones above the root of each note in a chord of this quality,
+    /// in ascending order.
+    pub fn intervals(&self) -> Vec<u8> {
+        self.tones()
+            .into_iter()
+            .map(|(_, semitones)| semitones)
+            .collect()
+    }
+
+    /// Each note in a chord of this quality as its degree above the root and
+    /// the semitones above the root, in ascending order. The degree says how
+    /// the note is spelled, so the minor seventh of C7 is the Bb a seventh
+    /// above C rather than A#, and the seventh of a diminished seventh is
+    /// lowered twice.
+    pub fn tones(&self) -> Vec<(u8, u8)> {
+        let mut tones = match self.triad {
+            Triad::Major => vec![(1, 0), (3, 4), (5, 7)],
+            Triad::Minor => vec![(1, 0), (3, 3), (5, 7)],
+            Triad::Diminished => vec![(1, 0), (3, 3), (5, 6)],
+            Triad::Augmented => vec![(1, 0), (3, 4), (5, 8)],
+            Triad::Power => vec![(1, 0), (5, 7)],
+        };
+        if let Some(sus) = self.sus {
+            tones.retain(|&(degree, _)| degree != 3);
+            tones.push(match sus {
+                Sus::Sus2 => (2, 2),
+                Sus::Sus4 => (4, 5),
+            });
+        }
+        for extension in &self.extensions {
+            tones.push(match extension {
+                Extension::Sixth => (6, 9),
+                Extension::Seventh if self.triad == Triad::Diminished => (7, 9),
+                Extension::Seventh => (7, 10),
+                Extension::MajorSeventh => (7, 11),
+                Extension::Ninth => (9, 14),
+                Extension::Eleventh => (11, 17),
+                Extension::Thirteenth => (13, 21),
+            });
+        }
+        for &add in &self.adds {
+            tones.push(match add {
+                2 => (2, 2),
+                4 => (4, 5),
+                6 => (6, 9),
+                9 => (9, 14),
+                11 => (11, 17),
+                13 => (13, 21),
+                _ => continue,
+            });
+        }
+        for alteration in &self.alterations {
+            let natural = match alteration.degree {
+                5 => 7,
+                9 => 14,
+                11 => 17,
+                13 => 21,
+                _ => continue,
+            };
+            let altered = (natural + alteration.accidental.as_int()) as u8;
+            if alteration.degree == 5 {
+                tones.retain(|&(degree, _)| degree != 5);
+            }
+            tones.push((alteration.degree, altered));
+        }
+        tones.sort_by_key(|&(_, semitones)| semitones);
+        tones.dedup_by_key(|&mut (_, semitones)| semitones);
+        tones
+    }
+}
+
+impl StructuredQuality {
+    /// Writes this quality with the symbols chosen in `style`, falling back to
+    /// the canonical symbols.
+    pub fn write_styled(&self, f: &mut impl fmt::Write, style: &ChordStyle) -> fmt::Result {
+        match self.triad {
+            Triad::Major => {}
+            Triad::Minor => write!(f, "{}", style.minor.as_deref().unwrap_or("m"))?,
+            Triad::Diminished => write!(f, "{}", style.diminished.as_deref().unwrap_or("dim"))?,
+            Triad::Augmented => write!(f, "{}", style.augmented.as_deref().unwrap_or("aug"))?,
+            Triad::Power => write!(f, "5")?,
+        }
+        if self.extensions.contains(&Extension::MajorSeventh) {
+            write!(f, "{}", style.major_seventh.as_deref().unwrap_or("maj"))?;
+        }
+        match self.extensions.last() {
+            Some(Extension::Sixth) => write!(f, "6")?,
+            Some(Extension::Seventh | Extension::MajorSeventh) => write!(f, "7")?,
+            Some(Extension::Ninth) => write!(f, "9")?,
+            Some(Extension::Eleventh) => write!(f, "11")?,
+            Some(Extension::Thirteenth) => write!(f, "13")?,
+            None => {}
+        }
+        match self.sus {
+            Some(Sus::Sus2) => write!(f, "sus2")?,
+            Some(Sus::Sus4) => write!(f, "sus4")?,
+            None => {}
+        }
+        for add in &self.adds {
+            write!(f, "add{add}")?;
+        }
+        for alteration in &self.alterations {
+            write!(f, "{}{}", alteration.accidental, alteration.degree)?;
+        }
+        if self.altered {
+            write!(f, "alt")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for StructuredQuality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_styled(f, &ChordStyle::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::theory::{
+        chords::ChordQuality,
+        qualities::{Extension::*, StructuredQuality, Sus, Triad},
+    };
+
+    fn parse(quality: &str) -> StructuredQuality {
+        ChordQuality(quality.to_owned()).parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_quality() {
+        assert_eq!(
+            parse(""),
+            StructuredQuality {
+                triad: Triad::Major,
+                extensions: vec![],
+                sus: None,
+                adds: vec![],
+                alterations: vec![],
+                altered: false,
+            }
+        );
+        assert_eq!(
+            parse("m7"),
+            StructuredQuality {
+                triad: Triad::Minor,
+                extensions: vec![Seventh],
+                sus: None,
+                adds: vec![],
+                alterations: vec![],
+                altered: false,
+            }
+        );
+        assert_eq!(
+            parse("mMaj9"),
+            StructuredQuality {
+                triad: Triad::Minor,
+                extensions: vec![MajorSeventh, Ninth],
+                sus: None,
+                adds: vec![],
+                alterations: vec![],
+                altered: false,
+            }
+        );
+        assert_eq!(
+            parse("7sus4add13"),
+            StructuredQuality {
+                triad: Triad::Major,
+                extensions: vec![Seventh],
+                sus: Some(Sus::Sus4),
+                adds: vec![13],
+                alterations: vec![],
+                altered: false,
+            }
+        );
+        assert_eq!(parse("+").triad, Triad::Augmented);
+        assert_eq!(parse("5").triad, Triad::Power);
+
+        assert!(ChordQuality("mmsus".to_owned()).parse().is_err());
+        assert!(ChordQuality("5m".to_owned()).parse().is_err());
+    }
+
+    #[test]
+    fn test_intervals() {
+        for (quality, intervals) in [
+            ("", &[0, 4, 7][..]),
+            ("m", &[0, 3, 7]),
+            ("dim", &[0, 3, 6]),
+            ("aug", &[0, 4, 8]),
+            ("5", &[0, 7]),
+            ("6", &[0, 4, 7, 9]),
+            ("m6", &[0, 3, 7, 9]),
+            ("7", &[0, 4, 7, 10]),
+            ("maj7", &[0, 4, 7, 11]),
+            ("m7", &[0, 3, 7, 10]),
+            ("dim7", &[0, 3, 6, 9]),
+            ("m7b5", &[0, 3, 6, 10]),
+            ("9", &[0, 4, 7, 10, 14]),
+            ("maj9", &[0, 4, 7, 11, 14]),
+            ("11", &[0, 4, 7, 10, 14, 17]),
+            ("13", &[0, 4, 7, 10, 14, 21]),
+            ("sus2", &[0, 2, 7]),
+            ("sus4", &[0, 5, 7]),
+            ("7sus4", &[0, 5, 7, 10]),
+            ("add9", &[0, 4, 7, 14]),
+            ("madd9", &[0, 3, 7, 14]),
+        ] {
+            assert_eq!(parse(quality).intervals(), intervals, "{quality:?}");
+        }
+    }
+
+    #[test]
+    fn test_tones() {
+        assert_eq!(parse("7").tones(), [(1, 0), (3, 4), (5, 7), (7, 10)]);
+        assert_eq!(parse("dim7").tones(), [(1, 0), (3, 3), (5, 6), (7, 9)]);
+        assert_eq!(parse("sus4").tones(), [(1, 0), (4, 5), (5, 7)]);
+        assert_eq!(parse("6").tones()[3], (6, 9));
+    }
+
+    #[test]
+    fn test_display_quality() {
+        for (input, canonical) in [
+            ("", ""),
+            ("min7", "m7"),
+            ("-", "m"),
+            ("Maj7", "maj7"),
+            ("+", "aug"),
+            ("sus", "sus4"),
+            ("madd9", "madd9"),
+        ] {
+            assert_eq!(parse(input).to_string(), canonical);
+        }
+    }
+}
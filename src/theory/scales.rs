@@ -1,43 +1,162 @@
-use std::fmt;
+use std::{cmp::Reverse, fmt};
 
-use crate::theory::notes::{Accidental, Letter, LetterNote, MidiPitch, Note};
+use crate::theory::{
+    chords::Chord,
+    notes::{Accidental, Letter, LetterNote, MidiPitch, Note},
+};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Scale(pub LetterNote);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ScaleDegree(u8, Accidental);
 
+/// The mode that scale degrees are counted in. The degrees of the mode itself
+/// are natural, so in a minor key the relative major is `3` rather than `b3`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Major,
+    NaturalMinor,
+}
+
+impl Scale {
+    /// The seven notes of this major scale, starting from the tonic. Each
+    /// letter is used once, so F# major has E# rather than F, and Gb major has
+    /// Cb rather than B.
+    pub fn notes(&self) -> [LetterNote; 7] {
+        std::array::from_fn(|i| ScaleDegree(i as u8 + 1, Accidental::NATURAL).in_key(*self))
+    }
+
+    /// The number of sharps or flats in the key signature of this major key.
+    pub fn accidental_count(&self) -> u8 {
+        self.notes()
+            .iter()
+            .filter(|note| note.accidental() != Accidental::NATURAL)
+            .count() as u8
+    }
+
+    /// The tonic of the minor key with the same notes as this major key, which
+    /// is its sixth degree. The relative minor of Gb is Eb rather than D#.
+    pub fn relative_minor(&self) -> Scale {
+        Scale(self.notes()[5])
+    }
+
+    /// Treating this scale's tonic as that of a minor key, the tonic of the
+    /// major key with the same notes, which is its third degree.
+    pub fn relative_major(&self) -> Scale {
+        Scale(ScaleDegree(3, Accidental::NATURAL).in_mode(*self, Mode::NaturalMinor))
+    }
+
+    /// The key signature of this major key: the number of sharps, or of flats
+    /// as a negative number, and the letters they apply to in the order they
+    /// are written on the staff. Returns `None` for keys such as D# major that
+    /// would need double sharps or flats.
+    pub fn key_signature(&self) -> Option<(i8, Vec<Letter>)> {
+        use Letter::*;
+
+        let notes = self.notes();
+        let accidentals = notes.map(|note| note.accidental().as_int());
+        if accidentals.iter().any(|accidental| accidental.abs() > 1) {
+            return None;
+        }
+        let count = accidentals.iter().sum::<i8>();
+        let order = if count > 0 {
+            [F, C, G, D, A, E, B]
+        } else {
+            [B, E, A, D, G, C, F]
+        };
+        let letters = order
+            .into_iter()
+            .filter(|&letter| {
+                notes
+                    .iter()
+                    .any(|note| note.letter() == letter && note.accidental() != Accidental::NATURAL)
+            })
+            .collect();
+        Some((count, letters))
+    }
+
+    /// Guesses the major key that best fits `chords`. Each chord scores for a
+    /// key when its root is in the scale, again when its quality matches the
+    /// diatonic triad on that degree, and again when it is the tonic chord.
+    /// Ties go to the key with fewer accidentals. Chords with numbered roots
+    /// are ignored, and `None` is returned if there is nothing to go on.
+    pub fn detect<'a>(chords: impl IntoIterator<Item = &'a Chord>) -> Option<Scale> {
+        let roots = chords
+            .into_iter()
+            .filter_map(|chord| match chord.root {
+                Note::Letter(root) => Some((root.as_midi(), chord.quality.is_minor())),
+                Note::Number(_) => None,
+            })
+            .collect::<Vec<_>>();
+        if roots.is_empty() {
+            return None;
+        }
+
+        let c = LetterNote(Letter::C, Accidental::NATURAL).as_midi();
+        (0..12)
+            .map(|semitones| Scale((c + semitones).as_letter()))
+            .max_by_key(|key| {
+                let score = roots
+                    .iter()
+                    .map(|&(root, minor)| {
+                        let interval = (root.as_int() - key.0.as_midi().as_int()).rem_euclid(12);
+                        match (interval, minor) {
+                            (0, false) => 3,
+                            (5 | 7, false) | (2 | 4 | 9, true) => 2,
+                            (11, _) | (0 | 5 | 7, true) | (2 | 4 | 9, false) => 1,
+                            _ => 0,
+                        }
+                    })
+                    .sum::<u32>();
+                (score, Reverse(key.accidental_count()))
+            })
+    }
+}
+
 impl ScaleDegree {
     pub fn new(degree: u8, accidental: Accidental) -> Self {
         assert!(
-            1 <= degree && degree <= 7,
+            (1..=7).contains(&degree),
             "Scale degree must be between 1 and 7"
         );
         ScaleDegree(degree, accidental)
     }
 
     pub fn in_key(self, key: Scale) -> LetterNote {
+        self.in_mode(key, Mode::Major)
+    }
+
+    pub fn in_mode(self, key: Scale, mode: Mode) -> LetterNote {
         let letter = key.0.letter() + (self.0 - 1) as i8;
-        LetterNote(letter, Accidental::NATURAL).add_accidentals_to_match(self.midi_in_key(key))
+        LetterNote(letter, Accidental::NATURAL)
+            .add_accidentals_to_match(self.midi_in_mode(key, mode))
     }
 
     pub fn midi_in_key(self, key: Scale) -> MidiPitch {
-        let delta = match self.0 {
-            1 => 0,
-            2 => 2,
-            3 => 4,
-            4 => 5,
-            5 => 7,
-            6 => 9,
-            7 => 11,
-            _ => unreachable!(),
-        };
+        self.midi_in_mode(key, Mode::Major)
+    }
+
+    pub fn midi_in_mode(self, key: Scale, mode: Mode) -> MidiPitch {
+        let delta = mode.semitones()[self.0 as usize - 1];
         key.0.as_midi() + delta + self.1.as_int()
     }
 
     pub fn add_accidentals_to_match(self, key: Scale, target: MidiPitch) -> Self {
-        let mut delta = (target.as_int() - self.in_key(key).as_midi().as_int()).rem_euclid(12);
+        self.add_accidentals_to_match_in_mode(key, Mode::Major, target)
+    }
+
+    pub fn add_accidentals_to_match_in_mode(
+        self,
+        key: Scale,
+        mode: Mode,
+        target: MidiPitch,
+    ) -> Self {
+        let natural = ScaleDegree(self.0, Accidental::NATURAL);
+        let mut delta = (target.as_int() - natural.midi_in_mode(key, mode).as_int()).rem_euclid(12);
         if delta > 6 {
             delta -= 12;
         }
@@ -45,20 +164,45 @@ impl ScaleDegree {
     }
 }
 
+impl Mode {
+    /// The semitones above the tonic of each degree of the scale.
+    pub const fn semitones(self) -> [i8; 7] {
+        match self {
+            Mode::Major => [0, 2, 4, 5, 7, 9, 11],
+            Mode::NaturalMinor => [0, 2, 3, 5, 7, 8, 10],
+        }
+    }
+}
+
 impl Note {
     pub fn as_scale_degree(self, key: Scale) -> ScaleDegree {
+        self.as_scale_degree_in_mode(key, Mode::Major)
+    }
+
+    pub fn as_scale_degree_in_mode(self, key: Scale, mode: Mode) -> ScaleDegree {
         match self {
-            Note::Letter(n) => n.as_scale_degree(key),
+            Note::Letter(n) => n.as_scale_degree_in_mode(key, mode),
             Note::Number(n) => n,
         }
     }
+
+    pub fn as_midi_in_key(self, key: Scale) -> MidiPitch {
+        match self {
+            Note::Letter(n) => n.as_midi(),
+            Note::Number(n) => n.midi_in_key(key),
+        }
+    }
 }
 
 impl LetterNote {
     pub fn as_scale_degree(self, key: Scale) -> ScaleDegree {
+        self.as_scale_degree_in_mode(key, Mode::Major)
+    }
+
+    pub fn as_scale_degree_in_mode(self, key: Scale, mode: Mode) -> ScaleDegree {
         self.letter()
             .as_natural_scale_degree(key)
-            .add_accidentals_to_match(key, self.as_midi())
+            .add_accidentals_to_match_in_mode(key, mode, self.as_midi())
     }
 }
 
@@ -111,4 +255,65 @@ mod test {
         );
         assert_eq!("Db".parse::<Scale>().unwrap(), Scale(LetterNote(D, FLAT)));
     }
+
+    #[test]
+    fn test_accidental_count() {
+        assert_eq!(Scale(C.natural()).accidental_count(), 0);
+        assert_eq!(Scale(G.natural()).accidental_count(), 1);
+        assert_eq!(Scale(F.natural()).accidental_count(), 1);
+        assert_eq!(Scale(F.sharp()).accidental_count(), 6);
+        assert_eq!(Scale(C.sharp()).accidental_count(), 7);
+
+        let mut keys = ["Db", "G", "E", "C", "Bb"].map(|k| k.parse::<Scale>().unwrap());
+        keys.sort_by_key(Scale::accidental_count);
+        assert_eq!(keys.map(|k| k.to_string()), ["C", "G", "Bb", "E", "Db"]);
+    }
+
+    #[test]
+    fn test_relative_keys() {
+        let relative_minor = |key: &str| key.parse::<Scale>().unwrap().relative_minor().to_string();
+        let relative_major = |key: &str| key.parse::<Scale>().unwrap().relative_major().to_string();
+
+        assert_eq!(relative_minor("C"), "A");
+        assert_eq!(relative_minor("Gb"), "Eb");
+        assert_eq!(relative_minor("F#"), "D#");
+        assert_eq!(relative_minor("Db"), "Bb");
+        assert_eq!(relative_major("A"), "C");
+        assert_eq!(relative_major("Eb"), "Gb");
+        assert_eq!(relative_major("D#"), "F#");
+        assert_eq!(relative_major("G#"), "B");
+    }
+
+    #[test]
+    fn test_key_signature() {
+        assert_eq!(Scale(C.natural()).key_signature(), Some((0, vec![])));
+        assert_eq!(Scale(G.natural()).key_signature(), Some((1, vec![F])));
+        assert_eq!(Scale(F.natural()).key_signature(), Some((-1, vec![B])));
+        assert_eq!(
+            Scale(G.flat()).key_signature(),
+            Some((-6, vec![B, E, A, D, G, C]))
+        );
+        assert_eq!(
+            Scale(C.sharp()).key_signature(),
+            Some((7, vec![F, C, G, D, A, E, B]))
+        );
+        assert_eq!(Scale(C.flat()).key_signature().unwrap().0, -7);
+        assert_eq!(Scale(D.sharp()).key_signature(), None);
+    }
+
+    #[test]
+    fn test_notes() {
+        let names = |key: &str| {
+            key.parse::<Scale>()
+                .unwrap()
+                .notes()
+                .map(|note| note.to_string())
+        };
+
+        assert_eq!(names("C"), ["C", "D", "E", "F", "G", "A", "B"]);
+        assert_eq!(names("F#"), ["F#", "G#", "A#", "B", "C#", "D#", "E#"]);
+        assert_eq!(names("Gb"), ["Gb", "Ab", "Bb", "Cb", "Db", "Eb", "F"]);
+        assert_eq!(Scale(F.sharp()).notes()[6], LetterNote(E, SHARP));
+        assert_eq!(Scale(G.flat()).notes()[3], LetterNote(C, FLAT));
+    }
 }